@@ -1,22 +1,51 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{ self, Token, TokenAccount, CloseAccount, Mint, Transfer as SplTransfer };
-use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::associated_token;
 use anchor_lang::solana_program::system_instruction;
-use pyth_solana_receiver_sdk::price_update::{ PriceUpdateV2 };
+use pyth_solana_receiver_sdk::price_update::{ Price, PriceUpdateV2 };
 use pyth_solana_receiver_sdk::price_update::get_feed_id_from_hex;
+use pyth_solana_receiver_sdk::error::GetPriceError;
 use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::{ ed25519_program, hash::hash, sysvar::instructions as sysvar_instructions };
+
+pub mod merkle;
 
 declare_id!("385YS1FGAQd8qGhiMsTnvJExTk7A6mgr8rNCRejQCPHi");
 
 const MIN_PURCHASE: u64 = 50;
 const MAX_PURCHASE: u64 = 5_000_000;
+const MAXIMUM_PRICE_AGE: u64 = 60; // 只接受 60s 内更新的价格
+const DEFAULT_MAX_PRICE_DEVIATION_BPS: u16 = 500; // spot 与 EMA 价格默认最多偏离 5%
+const BPS_DENOMINATOR: u64 = 10_000;
+const MAXIMUM_FALLBACK_PRICE_AGE: i64 = 600; // 管理员维护的备用价格 10 分钟内有效
+const PRICE_EXPONENT: i32 = -8; // 多个价格源统一换算到的价格精度
+const DEFAULT_MIN_PRICE_SOURCES: u8 = 1;
+const LEGACY_STATE_LEN: usize = 32 + 32 + 32 + 32; // 最初版本的 State 只有 admin 和三个 Mint 地址
+const MAX_PRICE_SOURCES: usize = 5; // 单次购买最多接受的价格源数量（含 price_update）
+const MAX_MANUAL_PRICE_DURATION: i64 = 24 * 60 * 60; // 人工价格最长有效 24 小时
+const USD_DECIMALS: u32 = 6; // USD 金额统一使用 6 位小数（micro-USD）
+const SPL_PRICE_IN_USD: u64 = 20_000; // 1 SCY = 0.02 USD（micro-USD）
+const LAMPORTS_PER_SOL_DECIMALS: u32 = 9; // 1 SOL = 10^9 lamports
+pub const MAX_PRICING_TIERS: usize = 8; // State 中最多可以配置的价格档位数量
+pub const MAX_PRICE_STEPS: usize = 8; // State 中最多可以配置的定时调价步数
+pub const MAX_KYC_TIERS: usize = 4; // State 中最多可以配置的 KYC 等级数量
+pub const MAX_VOLUME_BONUS_TIERS: usize = 4; // State 中最多可以配置的大额购买奖励档位数量
+pub const MAX_BATCH_RECIPIENTS: usize = 16; // buy_batch_with_spl 一次最多的接收人数量
+const BATCH_ACCOUNTS_PER_RECIPIENT: usize = 4; // 每个接收人在 remaining_accounts 中占用的账户数量
+const GATING_RATE_SCALE: u128 = 1_000_000; // gating_allocation_rate 的精度
+const CURVE_SLOPE_UNIT: u128 = 1_000_000; // 线性曲线的斜率按每售出 100 万 SCY 的涨价幅度配置
+const AUCTION_WEIGHT_SCALE: u128 = 1_000_000_000_000; // 荷兰拍统一结算时，付款数量 / 成交价格 的放大倍数
 
 //----------------------------------------------------结构声明----------------------------------------------------
 #[derive(Accounts)] // 定义 BuyScyWithSol 所需的账户
+#[instruction(lamports_to_pay: u64, options: PurchaseOptions)]
 pub struct BuySplWithSol<'info> {
     #[account(mut)]
-    pub user: Signer<'info>, // 用户，必须签名
+    pub user: Signer<'info>, // 用户，必须签名，支付购买资产和新建账户的租金
+
+    /// CHECK: 接收 SCY 的钱包，可以是托管方或支付合作方代为购买的客户钱包；不传时为 user 自己
+    /// 钱包上限、白名单、KYC 等按 recipient 统计
+    pub recipient: Option<UncheckedAccount<'info>>,
 
     #[account(mut, seeds = [b"state"], bump)]
     pub state: Account<'info, State>, // PDA账户，合约的全局状态账户，存储合约的全局数据，包括管理员、铸币地址等等
@@ -34,22 +63,130 @@ pub struct BuySplWithSol<'info> {
         init_if_needed,
         payer = user,
         associated_token::mint = mint,
-        associated_token::authority = user
+        associated_token::authority = recipient.as_ref().map_or(user.to_account_info(), ToAccountInfo::to_account_info)
     )]
-    pub user_spl_ata: Account<'info, TokenAccount>, // 用户的 SCY 代币账户，如果用户没有账户，则自动创建
+    pub user_spl_ata: Account<'info, TokenAccount>, // recipient 的 SCY 代币账户，如果没有账户，则由 user 付费自动创建
 
     #[account(address = associated_token::ID)]
     pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
 
     pub price_update: Account<'info, PriceUpdateV2>, // 预言机价格账户
+    pub manual_price: Option<Account<'info, ManualPrice>>, // 人工价格账户，仅在标记了预言机故障（oracle_down）且预言机价格不可用时生效
+
+    #[account(mut, seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Box<Account<'info, SaleStats>>, // 全局销售统计
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BuyerInfo::LEN,
+        seeds = [b"buyer", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub buyer_info: Box<Account<'info, BuyerInfo>>, // 买家的累计购买记录，首次购买时自动创建
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Receipt::LEN,
+        seeds = [b"receipt", recipient.as_ref().map_or(user.key(), Key::key).as_ref(), &buyer_info.purchase_count.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, Receipt>>, // 可选的成交回执账户，按买家和购买序号生成
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + VestingPosition::LEN,
+        seeds = [b"vesting", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub vesting_position: Option<Account<'info, VestingPosition>>, // 锁仓模式下记录用户购买的 SCY，其它模式可不传
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Allocation::LEN,
+        seeds = [b"allocation", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub allocation: Option<Account<'info, Allocation>>, // TGE 后领取模式下记录用户购买的 SCY，其它模式可不传
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Escrow::LEN,
+        seeds = [b"escrow", user.key().as_ref()],
+        bump
+    )]
+    pub escrow: Option<Account<'info, Escrow>>, // 软顶模式下记录用户支付的资产，用于募资失败时退款，其它模式可不传
+
+    #[account(mut, seeds = [b"sale_round", &[options.round_index.unwrap_or_default()]], bump)]
+    pub sale_round: Option<Account<'info, SaleRound>>, // 指定轮次购买时传入该轮次账户
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RoundPurchase::LEN,
+        seeds = [b"round_purchase".as_ref(), &[options.round_index.unwrap_or_default()], recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub round_purchase: Option<Account<'info, RoundPurchase>>, // 用户在该轮次的购买记录，指定轮次购买时传入
+
+    #[account(seeds = [b"allowlist", &[options.round_index.unwrap_or_default()], recipient.as_ref().map_or(user.key(), Key::key).as_ref()], bump)]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>, // 用户在该轮次的白名单记录，轮次要求白名单时传入
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + AuctionBid::LEN,
+        seeds = [b"auction_bid", user.key().as_ref()],
+        bump
+    )]
+    pub auction_bid: Option<Account<'info, AuctionBid>>, // 荷兰拍统一结算模式下记录用户的付款，其它模式可不传
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Order::LEN,
+        seeds = [b"order", user.key().as_ref(), &options.client_order_id.unwrap_or_default()],
+        bump
+    )]
+    pub order: Option<Account<'info, Order>>, // 按 (user, client_order_id) 生成的订单记录，重复的订单号无法再次创建；传入 client_order_id 时必须传入
+
+    /// CHECK: 指令 sysvar，用于读取同一笔交易中 KYC attester 的 Ed25519 签名验证指令
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub gating_token_account: Option<Account<'info, TokenAccount>>, // 用户持有的合作方代币账户，轮次开启持币门槛时必须传入
+
+    #[account(mut)]
+    pub referrer: Option<Account<'info, Referrer>>, // 推荐人的 Referrer 账户，不使用推荐时可不传
+
+    #[account(mut)]
+    pub promo_code: Option<Account<'info, PromoCode>>, // 优惠码账户，地址由 hash(code) 推导，不使用优惠码时可不传
+
+    /// CHECK: 用户的拒绝名单 PDA，地址固定可推导；账户存在即表示该钱包被禁止购买
+    #[account(seeds = [b"denied", user.key().as_ref()], bump)]
+    pub denied: UncheckedAccount<'info>,
+
+    /// CHECK: recipient 的拒绝名单 PDA，被禁止的钱包也不能通过他人代为购买
+    #[account(seeds = [b"denied", recipient.as_ref().map_or(user.key(), Key::key).as_ref()], bump)]
+    pub recipient_denied: UncheckedAccount<'info>,
+
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(token_amount: u64, options: PurchaseOptions)]
 pub struct BuySplWithSpl<'info> {
     #[account(mut)]
-    pub user: Signer<'info>, // 用户，必须签名
+    pub user: Signer<'info>, // 用户，必须签名，支付购买资产和新建账户的租金
+
+    /// CHECK: 接收 SCY 的钱包，可以是托管方或支付合作方代为购买的客户钱包；不传时为 user 自己
+    /// 钱包上限、白名单、KYC 等按 recipient 统计
+    pub recipient: Option<UncheckedAccount<'info>>,
 
     #[account(mut, seeds = [b"state"], bump)]
     pub state: Account<'info, State>, // PDA账户，合约的全局状态账户，存储合约的全局数据，包括管理员、铸币地址等等
@@ -75,15 +212,169 @@ pub struct BuySplWithSpl<'info> {
         init_if_needed,
         payer = user,
         associated_token::mint = mint,
-        associated_token::authority = user
+        associated_token::authority = recipient.as_ref().map_or(user.to_account_info(), ToAccountInfo::to_account_info)
     )]
-    pub user_spl_ata: Account<'info, TokenAccount>, // 用户的 SCY 代币账户，如果用户没有账户，则自动创建
+    pub user_spl_ata: Account<'info, TokenAccount>, // recipient 的 SCY 代币账户，如果没有账户，则由 user 付费自动创建
 
     #[account(address = associated_token::ID)]
     pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
 
     pub price_update: Account<'info, PriceUpdateV2>,
+    pub manual_price: Option<Account<'info, ManualPrice>>, // 人工价格账户，仅在标记了预言机故障（oracle_down）且预言机价格不可用时生效
+
+    #[account(mut, seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Box<Account<'info, SaleStats>>, // 全局销售统计
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + BuyerInfo::LEN,
+        seeds = [b"buyer", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub buyer_info: Box<Account<'info, BuyerInfo>>, // 买家的累计购买记录，首次购买时自动创建
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Receipt::LEN,
+        seeds = [b"receipt", recipient.as_ref().map_or(user.key(), Key::key).as_ref(), &buyer_info.purchase_count.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Option<Account<'info, Receipt>>, // 可选的成交回执账户，按买家和购买序号生成
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + VestingPosition::LEN,
+        seeds = [b"vesting", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub vesting_position: Option<Account<'info, VestingPosition>>, // 锁仓模式下记录用户购买的 SCY，其它模式可不传
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Allocation::LEN,
+        seeds = [b"allocation", recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub allocation: Option<Account<'info, Allocation>>, // TGE 后领取模式下记录用户购买的 SCY，其它模式可不传
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Escrow::LEN,
+        seeds = [b"escrow", user.key().as_ref()],
+        bump
+    )]
+    pub escrow: Option<Account<'info, Escrow>>, // 软顶模式下记录用户支付的资产，用于募资失败时退款，其它模式可不传
+
+    #[account(mut, seeds = [b"sale_round", &[options.round_index.unwrap_or_default()]], bump)]
+    pub sale_round: Option<Account<'info, SaleRound>>, // 指定轮次购买时传入该轮次账户
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RoundPurchase::LEN,
+        seeds = [b"round_purchase".as_ref(), &[options.round_index.unwrap_or_default()], recipient.as_ref().map_or(user.key(), Key::key).as_ref()],
+        bump
+    )]
+    pub round_purchase: Option<Account<'info, RoundPurchase>>, // 用户在该轮次的购买记录，指定轮次购买时传入
+
+    #[account(seeds = [b"allowlist", &[options.round_index.unwrap_or_default()], recipient.as_ref().map_or(user.key(), Key::key).as_ref()], bump)]
+    pub allowlist_entry: Option<Account<'info, AllowlistEntry>>, // 用户在该轮次的白名单记录，轮次要求白名单时传入
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + AuctionBid::LEN,
+        seeds = [b"auction_bid", user.key().as_ref()],
+        bump
+    )]
+    pub auction_bid: Option<Account<'info, AuctionBid>>, // 荷兰拍统一结算模式下记录用户的付款，其它模式可不传
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Order::LEN,
+        seeds = [b"order", user.key().as_ref(), &options.client_order_id.unwrap_or_default()],
+        bump
+    )]
+    pub order: Option<Account<'info, Order>>, // 按 (user, client_order_id) 生成的订单记录，重复的订单号无法再次创建；传入 client_order_id 时必须传入
+
+    /// CHECK: 指令 sysvar，用于读取同一笔交易中 KYC attester 的 Ed25519 签名验证指令
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    pub gating_token_account: Option<Account<'info, TokenAccount>>, // 用户持有的合作方代币账户，轮次开启持币门槛时必须传入
+
+    #[account(mut)]
+    pub referrer: Option<Account<'info, Referrer>>, // 推荐人的 Referrer 账户，不使用推荐时可不传
+
+    #[account(mut)]
+    pub promo_code: Option<Account<'info, PromoCode>>, // 优惠码账户，地址由 hash(code) 推导，不使用优惠码时可不传
+
+    /// CHECK: 用户的拒绝名单 PDA，地址固定可推导；账户存在即表示该钱包被禁止购买
+    #[account(seeds = [b"denied", user.key().as_ref()], bump)]
+    pub denied: UncheckedAccount<'info>,
+
+    /// CHECK: recipient 的拒绝名单 PDA，被禁止的钱包也不能通过他人代为购买
+    #[account(seeds = [b"denied", recipient.as_ref().map_or(user.key(), Key::key).as_ref()], bump)]
+    pub recipient_denied: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 BuyBatchWithSpl 所需的账户；remaining_accounts 先传入 price_source_count 个额外的价格源，
+// 再按 (recipient, recipient 的 SCY ATA, recipient 的拒绝名单 PDA, recipient 的 BuyerInfo PDA) 依次传入每个接收人的账户
+#[instruction(spl_amounts: Vec<u64>, price_source_count: u8, client_order_id: Option<[u8; 32]>)]
+pub struct BuyBatchWithSpl<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>, // 付款方，必须签名，支付 USDC/USDT 和新建 ATA 的租金
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(mut, seeds = [b"pda_usdc_ata"], bump)]
+    pub pda_usdc_ata: Account<'info, TokenAccount>, // 合约的 USDC 代币账户
+
+    #[account(mut, seeds = [b"pda_usdt_ata"], bump)]
+    pub pda_usdt_ata: Account<'info, TokenAccount>, // 合约的 USDT 代币账户
+
+    #[account(mut)]
+    pub user_token_ata: Account<'info, TokenAccount>, // 付款方的 USDC/USDT 支付账户
+
+    pub user_mint: Account<'info, Mint>, // USDC/USDT Mint地址
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户
+
+    pub price_update: Account<'info, PriceUpdateV2>,
+    pub manual_price: Option<Account<'info, ManualPrice>>, // 人工价格账户，仅在标记了预言机故障（oracle_down）且预言机价格不可用时生效
+
+    #[account(mut, seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Box<Account<'info, SaleStats>>, // 全局销售统计
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Order::LEN,
+        seeds = [b"order", user.key().as_ref(), &client_order_id.unwrap_or_default()],
+        bump
+    )]
+    pub order: Option<Account<'info, Order>>, // 按 (user, client_order_id) 生成的订单记录，重复的订单号无法再次创建；传入 client_order_id 时必须传入
+
+    /// CHECK: 付款方的拒绝名单 PDA，账户存在即表示该钱包被禁止购买
+    #[account(seeds = [b"denied", user.key().as_ref()], bump)]
+    pub denied: UncheckedAccount<'info>,
 
+    #[account(address = associated_token::ID)]
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
@@ -97,6 +388,26 @@ pub struct GetPrice<'info> {
     pub price_update: Account<'info, PriceUpdateV2>,
 }
 
+// 用于按当前价格档位询价
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>,
+
+    #[account(seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Account<'info, SaleStats>, // 全局销售统计，提供当前的累计售出数量
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户，用于读取精度
+}
+
+// 用于查询全局销售统计
+#[derive(Accounts)]
+pub struct GetStats<'info> {
+    #[account(seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Account<'info, SaleStats>,
+}
+
 // 以下是 state 这个PDA账户的数据结构
 #[account]
 pub struct State {
@@ -104,238 +415,3005 @@ pub struct State {
     pub usdc_mint: Pubkey,
     pub usdt_mint: Pubkey,
     pub mint: Pubkey, // SCY 代币的 Mint 地址
+    pub max_price_deviation_bps: u16, // spot 价格与 EMA 价格允许的最大偏离（基点），超过则拒绝购买
+    pub use_conservative_price: bool, // 为 true 时取 spot 与 EMA 中对合约更有利（更低）的价格
+    pub min_price_sources: u8, // 计算中位数时至少需要多少个未过期且互相一致的价格源
+    pub price_operator: Pubkey, // 价格操作员，和 admin 一样可以在预言机故障时设置人工价格
+    pub delivery_mode: DeliveryMode, // SCY 的发放方式
+    pub vesting_schedule: VestingSchedule, // 锁仓模式下的释放规则
+    pub tge_timestamp: i64, // TGE 时间，由管理员设置，为 0 表示尚未设置；锁仓释放和 TGE 后领取都从该时间开始
+    pub reserved_spl: u64, // pda_spl_ata 中已售出但尚未发放给用户的 SCY，不能再出售或被提取
+    pub soft_cap_usd: u64, // 软顶（micro-USD），为 0 表示未开启软顶模式
+    pub sale_end: i64, // 软顶模式下的销售结束时间
+    pub sale_status: SaleStatus, // 软顶模式下的募资结果，由管理员在 sale_end 之后调用 finalize_sale 确定
+    pub hard_cap_usd: u64, // 累计募资的硬顶（micro-USD），为 0 表示不限制
+    pub partial_fill_at_cap: bool, // 超过硬顶的购买：为 true 时按剩余额度部分成交，为 false 时直接拒绝
+    pub pricing_tier_count: u8, // 已配置的价格档位数量，为 0 时按 SPL_PRICE_IN_USD 统一定价
+    pub pricing_tiers: [PricingTier; MAX_PRICING_TIERS], // 按累计售出数量递增的价格档位
+    pub price_step_count: u8, // 已配置的定时调价步数，为 0 表示不按时间调价
+    pub price_steps: [PriceStep; MAX_PRICE_STEPS], // 按时间递增的价格，到达 timestamp 后自动生效
+    pub pricing_mode: PricingMode, // 定价方式
+    pub dutch_auction: DutchAuctionConfig, // 荷兰拍模式下的价格衰减参数
+    pub auction_totals: AuctionTotals, // 荷兰拍统一结算时全部买家的付款汇总，用于计算应保留的返还金额
+    pub bonding_curve: BondingCurveConfig, // 联合曲线模式下的曲线参数
+    pub merkle_root: [u8; 32], // 白名单 Merkle root，叶子为 (wallet, max_allocation)；全 0 表示不限制
+    pub kyc_attester: Pubkey, // KYC 服务商的签名公钥，为默认值时不要求 KYC
+    pub kyc_tier_count: u8, // 已配置的 KYC 等级数量
+    pub kyc_tier_limits: [u64; MAX_KYC_TIERS], // 每个 KYC 等级累计最多可以购买的 SCY（最小单位）
+    pub volume_bonus_count: u8, // 已配置的大额购买奖励档位数量，为 0 表示不开启
+    pub volume_bonuses: [VolumeBonus; MAX_VOLUME_BONUS_TIERS], // 按 min_usd 递增的大额购买奖励档位
+    pub referral_reward_bps: u16, // 推荐奖励占买家购买 SCY 数量的比例（基点），由 pda_spl_ata 额外支付
+    pub oracle_down: bool, // admin / price_operator 标记预言机故障，为 true 时预言机价格不可用的购买改用人工价格
+    pub round_required: bool, // 为 true 时每笔购买都必须指定一个进行中的销售轮次
+    pub vesting_schedule_locked: bool, // 第一笔锁仓购买后置为 true，此后释放规则不能再修改
 }
 
-#[derive(Accounts)] // 定义 InitializeStat 所需的账户 (合约部术后第一次调用，用于创建state账户并指定 admin 和 mint address)
-pub struct InitializeState<'info> {
-    #[account(init, payer = admin, space = 8 + 32 + 32 + 32 + 32, seeds = [b"state"], bump)]
-    pub state: Account<'info, State>,
-    #[account(mut)]
-    pub admin: Signer<'info>, //admin账户是mut，意味着可以在交易中修改其 SOL 余额
-    pub system_program: Program<'info, System>,
+impl State {
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 2 + 1 + 1 + 32 + 1 + VestingSchedule::LEN + 8 + 8 + 8 + 8 + 1 + 8 + 1 + 1 + PricingTier::LEN * MAX_PRICING_TIERS + 1 + PriceStep::LEN * MAX_PRICE_STEPS + 1 + DutchAuctionConfig::LEN + AuctionTotals::LEN + BondingCurveConfig::LEN + 32 + 32 + 1 + 8 * MAX_KYC_TIERS + 1 + VolumeBonus::LEN * MAX_VOLUME_BONUS_TIERS + 2 + 1 + 1 + 1;
 }
 
-#[derive(Accounts)]
-pub struct InitializePdaSol<'info> { // 用于储存 SOL 的PDA账户
-    #[account(mut)]
-    pub admin: Signer<'info>,
+// 价格档位：累计售出的 SCY 依次填满每个档位，最后一个档位之后沿用最后一档的价格
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PricingTier {
+    pub supply: u64, // 该档位可售出的 SCY 数量（最小单位）
+    pub price_usd: u64, // 该档位 1 SCY 的价格（micro-USD）
+}
 
-    #[account(
-        mut,
-        seeds = [b"pda_sol"], 
-        bump,
-    )]
-    pub pda_sol_account: SystemAccount<'info>, // 这是一个 SystemAccount PDA
-    pub system_program: Program<'info, System>,
+impl PricingTier {
+    pub const LEN: usize = 8 + 8;
 }
 
-#[derive(Accounts)] // 定义 InitializePdaSplAta 所需的账户，用于初始化 pda_spl_ata 账户，也即合约的 SCY 代币账户（PDA），用于存储和分发 SCY 代币
-pub struct InitializePdaSplAta<'info> {
-    #[account(
-        init,
-        payer = admin, // admin需要支付储存pda_spl_ata账户的租金给solana
-        seeds = [b"pda_spl_ata"],
-        bump,
-        token::mint = mint, // 确保该账户存储的代币必须是 mint 代币
-        token::authority = state // 该账户管理权只属于state
-    )]
-    pub pda_spl_ata: Account<'info, TokenAccount>, // SCY 代币的存储账户
-    pub mint: Account<'info, Mint>, // SCY 代币的mint 账户
-    #[account(seeds = [b"state"], bump)]
-    pub state: Account<'info, State>, // 合约的 全局状态账户（PDA）
-    #[account(mut)]
-    pub admin: Signer<'info>, // 管理员账户，必须签名
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+// 大额购买奖励档位：单笔购买金额达到 min_usd 时，额外获得 bonus_bps 比例的 SCY，取满足条件的最高档位
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VolumeBonus {
+    pub min_usd: u64, // 触发该档位的最低购买金额（micro-USD）
+    pub bonus_bps: u16, // 额外获得的 SCY 比例（基点）
 }
 
-#[derive(Accounts)] // 定义 InitializePdaUsdcAta 所需的账户，用于初始化 pda_usdc_ata 账户，也即合约的 USDC 代币账户（PDA），用于存储和管理收到的 USDC 代币
-pub struct InitializePdaUsdcAta<'info> {
-    #[account(
-        init,
-        payer = admin,
-        seeds = [b"pda_usdc_ata"],
-        bump,
-        token::mint = usdc_mint,
-        token::authority = state
-    )]
-    pub pda_usdc_ata: Account<'info, TokenAccount>,
-    pub usdc_mint: Account<'info, Mint>,
-    #[account(seeds = [b"state"], bump)]
-    pub state: Account<'info, State>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+impl VolumeBonus {
+    pub const LEN: usize = 8 + 2;
 }
 
-#[derive(Accounts)] // 定义 InitializePdaUsdtAta 所需的账户，用于初始化 pda_usdt_ata 账户，也即合约的 USDT 代币账户（PDA），用于存储和管理收到的 USDT 代币
-pub struct InitializePdaUsdtAta<'info> {
-    #[account(
-        init,
-        payer = admin,
-        seeds = [b"pda_usdt_ata"],
-        bump,
-        token::mint = usdt_mint,
-        token::authority = state
-    )]
-    pub pda_usdt_ata: Account<'info, TokenAccount>,
-    pub usdt_mint: Account<'info, Mint>,
-    #[account(seeds = [b"state"], bump)]
-    pub state: Account<'info, State>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+// 定时调价的一步：从 timestamp 开始，1 SCY 的价格为 price_usd，直到下一步生效
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PriceStep {
+    pub timestamp: i64,
+    pub price_usd: u64, // 1 SCY 的价格（micro-USD）
 }
 
-#[derive(Accounts)] // 定义 Deposit 所需的账户，即管理员将 SCY代币 存入 pda_spl_ata 账户
-pub struct Deposit<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>, // 管理员账户，必须对交易签名
+impl PriceStep {
+    pub const LEN: usize = 8 + 8;
+}
 
-    #[account(mut, seeds = [b"state"], bump)]
-    pub state: Account<'info, State>, // 合约的全局状态账户
+// 定价方式
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PricingMode {
+    #[default]
+    Fixed, // 默认价格，或按价格档位 / 定时调价
+    DutchAuction, // 荷兰拍：价格从 start_price 随时间衰减到 floor_price
+    BondingCurve, // 联合曲线：价格是累计售出数量的函数
+}
+
+// 联合曲线的形状
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BondingCurveKind {
+    #[default]
+    Linear, // 价格 = base_price + slope_per_million * 累计售出 / 100 万 SCY
+    Exponential, // 每售出 doubling_supply 价格翻倍，相邻两次翻倍之间线性插值
+}
+
+// 联合曲线参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BondingCurveConfig {
+    pub kind: BondingCurveKind,
+    pub base_price: u64, // 累计售出为 0 时 1 SCY 的价格（micro-USD）
+    pub slope_per_million: u64, // 线性曲线：每售出 100 万 SCY 的涨价幅度（micro-USD）
+    pub doubling_supply: u64, // 指数曲线：价格翻倍所需售出的 SCY 数量（最小单位）
+}
+
+impl BondingCurveConfig {
+    pub const LEN: usize = 1 + 8 + 8 + 8;
+}
+
+// 荷兰拍的价格衰减曲线
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuctionDecay {
+    #[default]
+    Linear, // 在 start_time 到 end_time 之间线性下降
+    Exponential, // 与 floor_price 的差值每过 half_life 秒减半，end_time 时降到 floor_price
+}
+
+// 荷兰拍参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DutchAuctionConfig {
+    pub start_price: u64, // 起始价格（micro-USD）
+    pub floor_price: u64, // 最低价格（micro-USD）
+    pub start_time: i64,
+    pub end_time: i64,
+    pub decay: AuctionDecay,
+    pub half_life: i64, // 指数衰减的半衰期（秒），线性衰减时不使用
+    pub uniform_clearing: bool, // 为 true 时所有买家按最终成交价结算，早期买家可以领取差价
+}
+
+impl DutchAuctionConfig {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 1 + 8 + 1;
+}
+
+// 某个支付资产在荷兰拍中的付款汇总
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AuctionPayments {
+    pub paid: u64, // 累计付款（lamports 或 USDC/USDT 最小单位）
+    pub weighted: u128, // 累计 付款 * AUCTION_WEIGHT_SCALE / 成交价格，乘以最终成交价即为按最终成交价应付的数量
+    pub rebated: u64, // 已返还的差价
+}
+
+impl AuctionPayments {
+    pub const LEN: usize = 8 + 16 + 8;
+}
+
+// 荷兰拍统一结算的全局汇总
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AuctionTotals {
+    pub clearing_price: u64, // 目前为止最低的成交价格（micro-USD），拍卖结束后即为最终成交价
+    pub sol: AuctionPayments,
+    pub usdc: AuctionPayments,
+    pub usdt: AuctionPayments,
+}
+
+impl AuctionTotals {
+    pub const LEN: usize = 8 + AuctionPayments::LEN * 3;
+}
+
+// 购买时传入的 KYC 证明，attester 对 (域标签, 程序地址, 买家钱包, expiry, tier) 的 Ed25519 签名放在同一笔交易的签名验证指令中
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct KycAttestation {
+    pub expiry: i64, // 证明的过期时间
+    pub tier: u8, // KYC 等级，对应 state.kyc_tier_limits 中的购买上限
+}
+
+impl KycAttestation {
+    // 签名消息的域标签，加上本程序的地址，防止 attester 为其它程序或用途签的消息在这里被重放
+    const DOMAIN: &'static [u8] = b"scy-transfer:kyc";
+
+    // attester 签名的消息：DOMAIN(16) || program_id(32) || buyer(32) || expiry(8, 小端序) || tier(1)
+    fn message(&self, buyer: &Pubkey) -> Vec<u8> {
+        let mut message = Vec::with_capacity(Self::DOMAIN.len() + 32 + 32 + 8 + 1);
+        message.extend_from_slice(Self::DOMAIN);
+        message.extend_from_slice(crate::ID.as_ref());
+        message.extend_from_slice(buyer.as_ref());
+        message.extend_from_slice(&self.expiry.to_le_bytes());
+        message.push(self.tier);
+        message
+    }
+}
+
+// 购买时传入的白名单 proof，证明 (买家钱包, max_allocation) 在 state.merkle_root 对应的白名单中
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AllowlistProof {
+    pub max_allocation: u64, // 该钱包累计最多可以购买的 SCY（最小单位）
+    pub proof: Vec<[u8; 32]>,
+}
+
+// 购买指令的可选参数，不需要的字段传默认值（None / false）
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PurchaseOptions {
+    pub client_ref: Option<[u8; 32]>, // 写入回执的客户端引用，仅在传入 receipt 账户时使用
+    pub allow_partial: bool, // 库存或额度不足时是否按可购买数量部分成交
+    pub round_index: Option<u8>, // 购买的销售轮次，不指定时按默认价格成交
+    pub allowlist_proof: Option<AllowlistProof>, // 开启 Merkle 白名单时必须传入
+    pub kyc_attestation: Option<KycAttestation>, // 开启 KYC 时必须传入
+    pub promo_code: Option<String>, // 优惠码明文，需要同时传入对应的 promo_code 账户
+    pub client_order_id: Option<[u8; 32]>, // 后端的订单号，传入时创建 order 账户
+}
+
+// 软顶模式下的募资状态
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaleStatus {
+    #[default]
+    Active, // 销售进行中，付款留在托管中
+    Succeeded, // 达到软顶：资金可以提取，SCY 可以领取
+    Failed, // 未达到软顶：买家可以调用 claim_refund 取回付款
+}
+
+// 用户支付所用的资产
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PaymentAsset {
+    Sol,
+    Usdc,
+    Usdt,
+}
+
+// SCY 的发放方式
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryMode {
+    #[default]
+    Immediate, // 购买后立即转给用户
+    Vesting, // 记入用户的 VestingPosition，按 vesting_schedule 线性释放
+    DeferredClaim, // 记入用户的 Allocation，TGE 之后由用户调用 claim_allocation 领取
+}
+
+// 锁仓释放规则：TGE 时释放 tge_unlock_bps，cliff 结束后剩余部分在 vesting_duration 内线性释放
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VestingSchedule {
+    pub tge_unlock_bps: u16, // TGE 时立即释放的比例（基点）
+    pub cliff_duration: i64, // TGE 之后的 cliff 时长（秒）
+    pub vesting_duration: i64, // cliff 结束后的线性释放时长（秒）
+}
+
+impl VestingSchedule {
+    pub const LEN: usize = 2 + 8 + 8;
+}
+
+// 用户的锁仓记录，购买时记入，claim_vested 时按释放进度领取
+#[account]
+pub struct VestingPosition {
+    pub owner: Pubkey,
+    pub total_amount: u64, // 累计记入的 SCY（最小单位）
+    pub claimed_amount: u64, // 已领取的 SCY
+    pub schedule: VestingSchedule, // 创建时的释放规则
+}
+
+impl VestingPosition {
+    pub const LEN: usize = 32 + 8 + 8 + VestingSchedule::LEN;
+}
+
+// 用户在 TGE 后领取模式下的待领取额度
+#[account]
+pub struct Allocation {
+    pub owner: Pubkey,
+    pub amount: u64, // 累计记入的 SCY（最小单位）
+    pub claimed_amount: u64, // 已领取的 SCY
+}
+
+impl Allocation {
+    pub const LEN: usize = 32 + 8 + 8;
+}
+
+// 软顶模式下每个买家的付款托管记录，按资产分别累计，募资失败时按原数量退回
+#[account]
+pub struct Escrow {
+    pub owner: Pubkey,
+    pub lamports: u64, // 累计支付的 SOL（lamports）
+    pub usdc_amount: u64, // 累计支付的 USDC（最小单位）
+    pub usdt_amount: u64, // 累计支付的 USDT（最小单位）
+}
+
+impl Escrow {
+    pub const LEN: usize = 32 + 8 + 8 + 8;
+}
+
+// 销售轮次（seed / private / public 等），每个轮次有独立的价格、时间、额度、白名单要求和释放规则
+#[account]
+pub struct SaleRound {
+    pub index: u8, // 轮次编号
+    pub price_usd: u64, // 该轮次 1 SCY 的价格（micro-USD）
+    pub start_time: i64,
+    pub end_time: i64,
+    pub allocation: u64, // 该轮次可售出的 SCY 总量（最小单位）
+    pub sold: u64, // 该轮次已售出的 SCY
+    pub per_wallet_cap: u64, // 每个钱包在该轮次最多购买的 SCY，为 0 表示不限制
+    pub allowlist_required: bool, // 为 true 时只有白名单中的钱包可以购买
+    pub vesting_schedule: VestingSchedule, // 该轮次购买的 SCY 从 TGE 开始的释放规则
+    pub gating_mint: Pubkey, // 该轮次的持币门槛要求持有的合作方代币 Mint，为默认值时不开启
+    pub gating_min_balance: u64, // 合作方代币的最低持有数量（最小单位）
+    pub gating_allocation_rate: u64, // 每个合作方代币最小单位可购买的 SCY 最小单位（乘以 GATING_RATE_SCALE），为 0 时不按持币数量分配额度
+}
+
+impl SaleRound {
+    pub const LEN: usize = 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + VestingSchedule::LEN + 32 + 8 + 8;
+}
+
+// 管理员创建或更新销售轮次时传入的参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SaleRoundConfig {
+    pub price_usd: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub allocation: u64,
+    pub per_wallet_cap: u64,
+    pub allowlist_required: bool,
+    pub vesting_schedule: VestingSchedule,
+}
+
+// 用户在某个轮次的购买记录，SCY 按该轮次的释放规则领取
+#[account]
+pub struct RoundPurchase {
+    pub owner: Pubkey,
+    pub round_index: u8,
+    pub purchased: u64, // 在该轮次累计购买的 SCY（最小单位）
+    pub claimed_amount: u64, // 已领取的 SCY
+}
+
+impl RoundPurchase {
+    pub const LEN: usize = 32 + 1 + 8 + 8;
+}
+
+// 轮次白名单记录，账户存在即表示该钱包可以参与该轮次
+#[account]
+pub struct AllowlistEntry {
+    pub wallet: Pubkey,
+    pub round_index: u8,
+}
+
+impl AllowlistEntry {
+    pub const LEN: usize = 32 + 1;
+}
+
+// 推荐人记录，由钱包自己注册；购买时指定推荐人，奖励记入 pending_rewards，通过 claim_referral_rewards 领取
+#[account]
+pub struct Referrer {
+    pub owner: Pubkey,
+    pub referred_purchases: u64, // 推荐的成交笔数
+    pub referred_spl: u64, // 推荐买家累计购买的 SCY（最小单位）
+    pub referred_usd: u64, // 推荐买家累计花费的 USD（micro-USD）
+    pub pending_rewards: u64, // 待领取的推荐奖励 SCY（最小单位）
+    pub claimed_rewards: u64, // 已领取的推荐奖励 SCY（最小单位）
+}
+
+impl Referrer {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8;
+}
+
+// 优惠码，链上只保存 sha256(code)，购买时传入明文校验
+#[account]
+pub struct PromoCode {
+    pub code_hash: [u8; 32],
+    pub bonus_bps: u16, // 额外发放的 SCY 占购买数量的比例（基点）
+    pub max_uses: u32, // 最多可以使用的次数
+    pub remaining_uses: u32, // 剩余可用次数
+    pub expiry: i64, // 过期时间
+}
+
+impl PromoCode {
+    pub const LEN: usize = 32 + 2 + 4 + 4 + 8;
+}
+
+// 拒绝名单记录，账户存在即表示该钱包被禁止购买
+#[account]
+pub struct Denied {
+    pub wallet: Pubkey,
+    pub denied_at: i64, // 加入拒绝名单的时间
+}
+
+impl Denied {
+    pub const LEN: usize = 32 + 8;
+}
+
+// 荷兰拍统一结算模式下每个买家的付款记录，拍卖结束后按最终成交价领取差价
+#[account]
+pub struct AuctionBid {
+    pub owner: Pubkey,
+    pub sol: AuctionPayments,
+    pub usdc: AuctionPayments,
+    pub usdt: AuctionPayments,
+}
+
+impl AuctionBid {
+    pub const LEN: usize = 32 + AuctionPayments::LEN * 3;
+}
+
+// 管理员维护的备用价格账户，每个价格 feed 一个，可作为额外价格源参与中位数计算
+#[account]
+pub struct FallbackPrice {
+    pub feed_id: [u8; 32], // 对应的 Pyth feed id
+    pub price: i64,
+    pub exponent: i32,
+    pub updated_at: i64, // 最后一次更新的时间戳，超过 MAXIMUM_FALLBACK_PRICE_AGE 视为过期
+}
+
+impl FallbackPrice {
+    pub const LEN: usize = 32 + 8 + 4 + 8;
+}
+
+// 全局销售统计，每次购买时更新（提款不会影响这里的数据）
+#[account]
+pub struct SaleStats {
+    pub spl_sold: u64, // 已售出的 SCY 数量（最小单位）
+    pub sol_raised: u64, // 累计收到的 SOL（lamports）
+    pub usdc_raised: u64, // 累计收到的 USDC（最小单位）
+    pub usdt_raised: u64, // 累计收到的 USDT（最小单位）
+    pub usd_raised: u64, // 按成交时价格折算的累计 USD 金额（micro-USD）
+    pub buyer_count: u64, // 买家数量（按钱包去重）
+    pub last_purchase_slot: u64, // 最后一次购买所在的 slot
+}
+
+impl SaleStats {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8 + 8;
+}
+
+// 每个买家的累计购买记录
+#[account]
+pub struct BuyerInfo {
+    pub wallet: Pubkey,
+    pub spl_purchased: u64, // 累计购买的 SCY 数量（最小单位）
+    pub usd_spent: u64, // 累计花费的 USD 金额（micro-USD）
+    pub purchase_count: u64, // 购买次数
+}
+
+impl BuyerInfo {
+    pub const LEN: usize = 32 + 8 + 8 + 8;
+}
+
+// 单次成交的回执，用于对账；导出后买家可以关闭账户取回租金
+#[account]
+pub struct Receipt {
+    pub buyer: Pubkey,
+    pub index: u64, // 买家的第几次购买（从 0 开始）
+    pub payment_mint: Pubkey, // 支付代币的 Mint 地址，SOL 支付时为 native mint
+    pub payment_amount: u64,
+    pub spl_amount: u64, // 发放的 SCY 数量（最小单位）
+    pub price: i64, // 成交使用的支付资产价格
+    pub exponent: i32,
+    pub timestamp: i64,
+    pub client_ref: Option<[u8; 32]>, // 可选的客户端订单引用
+    pub payer: Pubkey, // 付款并支付回执租金的钱包，代他人购买时与 buyer 不同，关闭回执时租金退回给它
+}
+
+impl Receipt {
+    pub const LEN: usize = 32 + 8 + 32 + 8 + 8 + 8 + 4 + 8 + (1 + 32) + 32;
+}
+
+// 客户端订单记录，按 (买家, client_order_id) 生成，用于防止后端重试导致重复扣款
+// 与 Receipt.client_ref 分开：client_ref 只是写入回执的对账备注，回执按 recipient 和购买序号生成，无法防止重复提交；
+// client_order_id 是付款方的订单号，决定 Order 的地址。两者可以传入同一个值
+#[account]
+pub struct Order {
+    pub buyer: Pubkey,
+    pub client_order_id: [u8; 32],
+    pub payment_mint: Pubkey, // 支付代币的 Mint 地址，SOL 支付时为 native mint
+    pub payment_amount: u64,
+    pub spl_amount: u64, // 成交的 SCY 数量（最小单位）
+    pub timestamp: i64,
+}
+
+impl Order {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 8;
+}
+
+// 预言机故障时由 admin / price_operator 设置的紧急人工价格，每个价格 feed 一个，到期后失效
+#[account]
+pub struct ManualPrice {
+    pub feed_id: [u8; 32], // 对应的 Pyth feed id
+    pub price: i64,
+    pub exponent: i32,
+    pub updated_at: i64,
+    pub expires_at: i64, // 过期时间戳
+    pub set_by: Pubkey, // 设置该价格的账户
+}
+
+impl ManualPrice {
+    pub const LEN: usize = 32 + 8 + 4 + 8 + 8 + 32;
+}
+
+// 批量购买成功后发出的事件，每个接收人的数量见 spl_amounts
+#[event]
+pub struct BatchPurchased {
+    pub buyer: Pubkey, // 付款方
+    pub payment_mint: Pubkey,
+    pub payment_amount: u64, // 按全部接收人的 SCY 总数一次计价后实际支付的数量
+    pub spl_amount: u64, // 发放的 SCY 总数（最小单位）
+    pub recipients: Vec<Pubkey>,
+    pub spl_amounts: Vec<u64>,
+    pub client_order_id: Option<[u8; 32]>, // 后端的订单号，用于与订单数据库对账
+    pub price: i64,
+    pub exponent: i32,
+    pub manual_price: bool,
+    pub timestamp: i64,
+}
+
+// 每次购买成功后发出的事件，便于用户和审计方核对成交
+#[event]
+pub struct SplPurchased {
+    pub buyer: Pubkey,
+    pub recipient: Pubkey, // 接收 SCY 的钱包，为自己购买时与 buyer 相同
+    pub payment_mint: Pubkey, // 支付代币的 Mint 地址，SOL 支付时为 native mint
+    pub requested_payment_amount: u64, // 用户请求支付的数量
+    pub requested_spl_amount: u64, // 按请求支付数量计算的 SCY 数量
+    pub payment_amount: u64, // 实际支付的数量（lamports 或 USDC/USDT 最小单位），部分成交时小于请求数量
+    pub spl_amount: u64, // 实际购买的 SCY 数量（最小单位），包含大额购买奖励和优惠码奖励
+    pub bonus_spl_amount: u64, // spl_amount 中由优惠码额外发放的 SCY 数量（最小单位）
+    pub client_order_id: Option<[u8; 32]>, // 后端的订单号，用于与订单数据库对账
+    pub price: i64, // 本次成交使用的支付资产价格
+    pub exponent: i32,
+    pub manual_price: bool, // 为 true 表示预言机不可用，本次成交使用了人工价格
+    pub timestamp: i64,
+}
+
+#[derive(Accounts)] // 定义 InitializeStat 所需的账户 (合约部术后第一次调用，用于创建state账户并指定 admin 和 mint address)
+pub struct InitializeState<'info> {
+    #[account(init, payer = admin, space = 8 + State::LEN, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub admin: Signer<'info>, //admin账户是mut，意味着可以在交易中修改其 SOL 余额
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePdaSol<'info> { // 用于储存 SOL 的PDA账户
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"pda_sol"], 
+        bump,
+    )]
+    pub pda_sol_account: SystemAccount<'info>, // 这是一个 SystemAccount PDA
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 InitializePdaSplAta 所需的账户，用于初始化 pda_spl_ata 账户，也即合约的 SCY 代币账户（PDA），用于存储和分发 SCY 代币
+pub struct InitializePdaSplAta<'info> {
+    #[account(
+        init,
+        payer = admin, // admin需要支付储存pda_spl_ata账户的租金给solana
+        seeds = [b"pda_spl_ata"],
+        bump,
+        token::mint = mint, // 确保该账户存储的代币必须是 mint 代币
+        token::authority = state // 该账户管理权只属于state
+    )]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // SCY 代币的存储账户
+    pub mint: Account<'info, Mint>, // SCY 代币的mint 账户
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的 全局状态账户（PDA）
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，必须签名
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)] // 定义 InitializePdaUsdcAta 所需的账户，用于初始化 pda_usdc_ata 账户，也即合约的 USDC 代币账户（PDA），用于存储和管理收到的 USDC 代币
+pub struct InitializePdaUsdcAta<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"pda_usdc_ata"],
+        bump,
+        token::mint = usdc_mint,
+        token::authority = state
+    )]
+    pub pda_usdc_ata: Account<'info, TokenAccount>,
+    pub usdc_mint: Account<'info, Mint>,
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)] // 定义 InitializePdaUsdtAta 所需的账户，用于初始化 pda_usdt_ata 账户，也即合约的 USDT 代币账户（PDA），用于存储和管理收到的 USDT 代币
+pub struct InitializePdaUsdtAta<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"pda_usdt_ata"],
+        bump,
+        token::mint = usdt_mint,
+        token::authority = state
+    )]
+    pub pda_usdt_ata: Account<'info, TokenAccount>,
+    pub usdt_mint: Account<'info, Mint>,
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)] // 定义 InitializeSaleStats 所需的账户，用于创建全局销售统计账户
+pub struct InitializeSaleStats<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + SaleStats::LEN,
+        seeds = [b"sale_stats"],
+        bump
+    )]
+    pub sale_stats: Account<'info, SaleStats>,
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，必须签名
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 Deposit 所需的账户，即管理员将 SCY代币 存入 pda_spl_ata 账户
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，必须对交易签名
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, associated_token::mint = state.mint, associated_token::authority = admin)]
+    pub admin_ata: Account<'info, TokenAccount>, // 管理员的 SCY 代币账户
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)] // 定义 Withdraw 所需的账户
+pub struct Withdraw<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，必须对交易签名
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, associated_token::mint = state.mint, associated_token::authority = admin)]
+    pub admin_ata: Account<'info, TokenAccount>, // 管理员的 SCY 代币账户
+    #[account(mut, associated_token::mint = state.usdc_mint, associated_token::authority = admin)]
+    pub admin_usdc_ata: Account<'info, TokenAccount>, // 管理员的 USDC 代币账户
+
+    #[account(mut, associated_token::mint = state.usdt_mint, associated_token::authority = admin)]
+    pub admin_usdt_ata: Account<'info, TokenAccount>, // 管理员的 USDT 代币账户
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(mut, seeds = [b"pda_usdc_ata"], bump)]
+    pub pda_usdc_ata: Account<'info, TokenAccount>, // 合约的 USDC 代币账户
+
+    #[account(mut, seeds = [b"pda_usdt_ata"], bump)]
+    pub pda_usdt_ata: Account<'info, TokenAccount>, // 合约的 USDT 代币账户
+
+    #[account(mut, seeds = [b"pda_sol"], bump)]
+    pub pda_sol_account: SystemAccount<'info>, // 合约的SOL账户
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 UpdateAdmin 所需的账户
+pub struct UpdateAdmin<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub current_admin: Signer<'info>, // 当前管理员账户，必须签名交易
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 UpdateConfig 所需的账户，管理员修改 state 中的销售配置
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    pub admin: Signer<'info>, // 管理员账户，必须签名交易
+}
+
+#[derive(Accounts)] // 定义 MigrateState 所需的账户，State 增加字段后由管理员付费把旧的 state 账户扩容到 8 + State::LEN
+pub struct MigrateState<'info> {
+    /// CHECK: 旧布局的账户无法按新的 State 反序列化，在指令中校验 discriminator 和管理员后再扩容
+    #[account(mut, seeds = [b"state"], bump, owner = crate::ID)]
+    pub state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，必须签名交易，支付扩容所需的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 SetFallbackPrice 所需的账户，管理员创建或更新某个 feed 的备用价格
+#[instruction(feed_id: [u8; 32])]
+pub struct SetFallbackPrice<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FallbackPrice::LEN,
+        seeds = [b"fallback_price", feed_id.as_ref()],
+        bump
+    )]
+    pub fallback_price: Account<'info, FallbackPrice>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，支付备用价格账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 SetOracleDown 所需的账户，admin 或 price_operator 标记预言机故障
+pub struct SetOracleDown<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    pub operator: Signer<'info>, // admin 或 price_operator，必须签名交易
+}
+
+#[derive(Accounts)] // 定义 SetManualPrice 所需的账户，admin 或 price_operator 设置某个 feed 的人工价格
+#[instruction(feed_id: [u8; 32])]
+pub struct SetManualPrice<'info> {
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + ManualPrice::LEN,
+        seeds = [b"manual_price", feed_id.as_ref()],
+        bump
+    )]
+    pub manual_price: Account<'info, ManualPrice>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub operator: Signer<'info>, // admin 或 price_operator，支付人工价格账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 ClaimVested 所需的账户，用户领取已释放的锁仓 SCY
+pub struct ClaimVested<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 锁仓记录的所有者，必须签名
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"vesting", owner.key().as_ref()], bump, has_one = owner)]
+    pub vesting_position: Account<'info, VestingPosition>,
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub user_spl_ata: Account<'info, TokenAccount>, // 用户的 SCY 代币账户，如果用户没有账户，则自动创建
+
+    #[account(address = associated_token::ID)]
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 ClaimAllocation 所需的账户，用户在 TGE 之后领取购买的 SCY
+pub struct ClaimAllocation<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 额度的所有者，必须签名
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"allocation", owner.key().as_ref()], bump, has_one = owner)]
+    pub allocation: Account<'info, Allocation>,
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub user_spl_ata: Account<'info, TokenAccount>, // 用户的 SCY 代币账户，如果用户没有账户，则自动创建
+
+    #[account(address = associated_token::ID)]
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 SetSoftCap 所需的账户，管理员在销售开始前开启软顶模式
+pub struct SetSoftCap<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Account<'info, SaleStats>, // 全局销售统计，用于确认销售尚未开始
+
+    pub admin: Signer<'info>, // 管理员账户，必须签名交易
+}
+
+#[derive(Accounts)] // 定义 FinalizeSale 所需的账户，sale_end 之后任何人都可以确定软顶募资结果
+pub struct FinalizeSale<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(seeds = [b"sale_stats"], bump)]
+    pub sale_stats: Account<'info, SaleStats>, // 全局销售统计，用于比较累计募资与软顶
+}
+
+#[derive(Accounts)] // 定义 ClaimRefund 所需的账户，募资失败后用户取回托管的付款
+pub struct ClaimRefund<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 托管记录的所有者，必须签名，并接收退回的 SOL 和账户租金
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"escrow", owner.key().as_ref()], bump, has_one = owner, close = owner)]
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(mut, seeds = [b"allocation", owner.key().as_ref()], bump, has_one = owner, close = owner)]
+    pub allocation: Account<'info, Allocation>, // 退款后释放为该用户预留的 SCY
+
+    #[account(mut, seeds = [b"pda_sol"], bump)]
+    pub pda_sol_account: SystemAccount<'info>, // 合约的SOL账户
+
+    #[account(mut, seeds = [b"pda_usdc_ata"], bump)]
+    pub pda_usdc_ata: Account<'info, TokenAccount>, // 合约的 USDC 代币账户
+
+    #[account(mut, seeds = [b"pda_usdt_ata"], bump)]
+    pub pda_usdt_ata: Account<'info, TokenAccount>, // 合约的 USDT 代币账户
+
+    #[account(mut, token::mint = state.usdc_mint, token::authority = owner)]
+    pub user_usdc_ata: Option<Account<'info, TokenAccount>>, // 用户接收退款的 USDC 账户，用 USDC 付过款时必须传入
+
+    #[account(mut, token::mint = state.usdt_mint, token::authority = owner)]
+    pub user_usdt_ata: Option<Account<'info, TokenAccount>>, // 用户接收退款的 USDT 账户，用 USDT 付过款时必须传入
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 SetSaleRound 所需的账户，管理员创建或更新某个销售轮次
+#[instruction(index: u8)]
+pub struct SetSaleRound<'info> {
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + SaleRound::LEN,
+        seeds = [b"sale_round".as_ref(), &[index]],
+        bump
+    )]
+    pub sale_round: Account<'info, SaleRound>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，支付轮次账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 SetTokenGate 所需的账户，管理员为已创建的销售轮次配置持币门槛
+#[instruction(round_index: u8)]
+pub struct SetTokenGate<'info> {
+    #[account(mut, seeds = [b"sale_round".as_ref(), &[round_index]], bump)]
+    pub sale_round: Account<'info, SaleRound>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    pub admin: Signer<'info>, // 管理员账户，必须签名交易
+}
+
+#[derive(Accounts)] // 定义 AddToAllowlist 所需的账户，管理员把钱包加入某个轮次的白名单
+#[instruction(round_index: u8, wallet: Pubkey)]
+pub struct AddToAllowlist<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + AllowlistEntry::LEN,
+        seeds = [b"allowlist".as_ref(), &[round_index], wallet.as_ref()],
+        bump
+    )]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，支付白名单账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 RemoveFromAllowlist 所需的账户，管理员把钱包移出白名单并取回租金
+pub struct RemoveFromAllowlist<'info> {
+    #[account(mut, close = admin)]
+    pub allowlist_entry: Account<'info, AllowlistEntry>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，接收退回的租金
+}
+
+#[derive(Accounts)] // 定义 RegisterReferrer 所需的账户，钱包为自己注册推荐人账户
+pub struct RegisterReferrer<'info> {
+    #[account(init, payer = owner, space = 8 + Referrer::LEN, seeds = [b"referrer", owner.key().as_ref()], bump)]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>, // 推荐人钱包，支付账户租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 ReleaseReferralRewards 所需的账户，软顶募资失败后释放推荐人待领取奖励的预留
+pub struct ReleaseReferralRewards<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"referrer", referrer.owner.as_ref()], bump)]
+    pub referrer: Account<'info, Referrer>,
+}
+
+#[derive(Accounts)] // 定义 ClaimReferralRewards 所需的账户，推荐人领取累计的推荐奖励
+pub struct ClaimReferralRewards<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 推荐人钱包，必须签名
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"referrer", owner.key().as_ref()], bump, has_one = owner)]
+    pub referrer: Account<'info, Referrer>,
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub user_spl_ata: Account<'info, TokenAccount>, // 推荐人的 SCY 代币账户，如果没有账户，则自动创建
+
+    #[account(address = associated_token::ID)]
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 RegisterPromoCode 所需的账户，管理员登记优惠码的哈希
+#[instruction(code_hash: [u8; 32])]
+pub struct RegisterPromoCode<'info> {
+    #[account(init, payer = admin, space = 8 + PromoCode::LEN, seeds = [b"promo_code".as_ref(), code_hash.as_ref()], bump)]
+    pub promo_code: Account<'info, PromoCode>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，支付优惠码账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 RemovePromoCode 所需的账户，管理员作废优惠码并取回租金
+pub struct RemovePromoCode<'info> {
+    #[account(mut, close = admin)]
+    pub promo_code: Account<'info, PromoCode>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，接收退回的租金
+}
+
+#[derive(Accounts)] // 定义 AddToDenylist 所需的账户，管理员禁止某个钱包购买
+#[instruction(wallet: Pubkey)]
+pub struct AddToDenylist<'info> {
+    #[account(init, payer = admin, space = 8 + Denied::LEN, seeds = [b"denied", wallet.as_ref()], bump)]
+    pub denied: Account<'info, Denied>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，支付拒绝名单账户的租金
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 RemoveFromDenylist 所需的账户，管理员解除对钱包的禁止并取回租金
+pub struct RemoveFromDenylist<'info> {
+    #[account(mut, close = admin)]
+    pub denied: Account<'info, Denied>,
+
+    #[account(seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut)]
+    pub admin: Signer<'info>, // 管理员账户，接收退回的租金
+}
+
+#[derive(Accounts)] // 定义 ClaimRoundVested 所需的账户，用户领取某个轮次中已释放的 SCY
+#[instruction(round_index: u8)]
+pub struct ClaimRoundVested<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 购买记录的所有者，必须签名
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(seeds = [b"sale_round", &[round_index]], bump)]
+    pub sale_round: Account<'info, SaleRound>,
+
+    #[account(
+        mut,
+        seeds = [b"round_purchase", &[round_index], owner.key().as_ref()],
+        bump,
+        has_one = owner
+    )]
+    pub round_purchase: Account<'info, RoundPurchase>,
+
+    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
+    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+
+    #[account(address = state.mint)]
+    pub mint: Account<'info, Mint>, // SCY 代币的 Mint 账户
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub user_spl_ata: Account<'info, TokenAccount>, // 用户的 SCY 代币账户，如果用户没有账户，则自动创建
+
+    #[account(address = associated_token::ID)]
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 ClaimAuctionRebate 所需的账户，荷兰拍结束后用户按最终成交价领取差价
+pub struct ClaimAuctionRebate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>, // 付款记录的所有者，必须签名，并接收返还的 SOL 和账户租金
+
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+
+    #[account(mut, seeds = [b"auction_bid", owner.key().as_ref()], bump, has_one = owner, close = owner)]
+    pub auction_bid: Account<'info, AuctionBid>,
+
+    #[account(mut, seeds = [b"pda_sol"], bump)]
+    pub pda_sol_account: SystemAccount<'info>, // 合约的SOL账户
+
+    #[account(mut, seeds = [b"pda_usdc_ata"], bump)]
+    pub pda_usdc_ata: Account<'info, TokenAccount>, // 合约的 USDC 代币账户
+
+    #[account(mut, seeds = [b"pda_usdt_ata"], bump)]
+    pub pda_usdt_ata: Account<'info, TokenAccount>, // 合约的 USDT 代币账户
+
+    #[account(mut, token::mint = state.usdc_mint, token::authority = owner)]
+    pub user_usdc_ata: Option<Account<'info, TokenAccount>>, // 用户接收 USDC 差价的账户，用 USDC 付过款时必须传入
+
+    #[account(mut, token::mint = state.usdt_mint, token::authority = owner)]
+    pub user_usdt_ata: Option<Account<'info, TokenAccount>>, // 用户接收 USDT 差价的账户，用 USDT 付过款时必须传入
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)] // 定义 CloseReceipt 所需的账户，付款方关闭成交回执并取回自己支付的租金
+pub struct CloseReceipt<'info> {
+    #[account(mut, close = payer, has_one = payer)]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // 购买时支付回执租金的付款方，接收退回的租金
+}
+
+#[derive(Accounts)] // 定义 CloseOrder 所需的账户，买家关闭自己的订单记录并取回租金
+pub struct CloseOrder<'info> {
+    #[account(mut, close = buyer, has_one = buyer)]
+    pub order: Account<'info, Order>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>, // 订单所属的付款方，接收退回的租金
+}
+
+#[derive(Accounts)]
+pub struct ClosePda<'info> {
+    #[account(mut, seeds = [b"state"], bump)]
+    pub state: Account<'info, State>, // 合约的全局状态账户
+    #[account(mut)]
+    pub pda_account: Account<'info, TokenAccount>, // USDC/SCY/USDT PDA
+    #[account(mut)]
+    pub admin: Signer<'info>, // 接收 SOL Rent 的 Admin Wallet
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseState<'info> {
+    #[account(mut, close = admin)]
+    pub state: Account<'info, State>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+// ----------------------------------------------------辅助函数----------------------------------------------------
+// 两个价格之间的偏离，以 b 为基准，单位为基点
+fn deviation_bps(a: i64, b: i64) -> u128 {
+    ((a.abs_diff(b) as u128) * (BPS_DENOMINATOR as u128)) / (b.unsigned_abs() as u128)
+}
+
+// 把价格换算到统一的 PRICE_EXPONENT 精度，便于不同价格源之间比较和取中位数
+fn normalize_price(price: i64, exponent: i32) -> Result<i64> {
+    let shift = exponent - PRICE_EXPONENT;
+    let factor = (10i128).checked_pow(shift.unsigned_abs()).ok_or(CustomError::MathOverflow)?;
+    let scaled = if shift >= 0 {
+        (price as i128).checked_mul(factor).ok_or(CustomError::MathOverflow)?
+    } else {
+        (price as i128) / factor
+    };
+    Ok(i64::try_from(scaled).map_err(|_| CustomError::MathOverflow)?)
+}
+
+// 把支付资产的数量按价格换算成 micro-USD：amount / 10^amount_decimals * price * 10^exponent
+fn to_usd_value(amount: u64, amount_decimals: u32, price: &Price) -> Result<u64> {
+    require!(price.price > 0, CustomError::InvalidPrice);
+    let shift = (USD_DECIMALS as i32) + price.exponent - (amount_decimals as i32);
+    let factor = (10u128).checked_pow(shift.unsigned_abs()).ok_or(CustomError::MathOverflow)?;
+    let value = (amount as u128).checked_mul(price.price as u128).ok_or(CustomError::MathOverflow)?;
+    let value = if shift >= 0 {
+        value.checked_mul(factor).ok_or(CustomError::MathOverflow)?
+    } else {
+        value / factor
+    };
+    Ok(u64::try_from(value).map_err(|_| CustomError::MathOverflow)?)
+}
+
+// to_usd_value 的逆运算：折合 usd_value（micro-USD）至少需要支付的资产数量，向上取整，对合约有利
+fn from_usd_value(usd_value: u64, amount_decimals: u32, price: &Price) -> Result<u64> {
+    require!(price.price > 0, CustomError::InvalidPrice);
+    let shift = (USD_DECIMALS as i32) + price.exponent - (amount_decimals as i32);
+    let factor = (10u128).checked_pow(shift.unsigned_abs()).ok_or(CustomError::MathOverflow)?;
+    let (numerator, denominator) = if shift >= 0 {
+        (usd_value as u128, (price.price as u128).checked_mul(factor).ok_or(CustomError::MathOverflow)?)
+    } else {
+        ((usd_value as u128).checked_mul(factor).ok_or(CustomError::MathOverflow)?, price.price as u128)
+    };
+    Ok(u64::try_from(numerator.div_ceil(denominator)).map_err(|_| CustomError::MathOverflow)?)
+}
+
+// 按 SCY 单价（micro-USD）把 micro-USD 金额换算成 SCY 数量（最小单位），向下取整
+fn usd_to_spl_amount(usd_value: u64, spl_precision: u64, spl_price_usd: u64) -> Result<u64> {
+    let amount = ((usd_value as u128) * (spl_precision as u128)) / (spl_price_usd as u128);
+    Ok(u64::try_from(amount).map_err(|_| CustomError::MathOverflow)?)
+}
+
+// 按价格档位分段计价：从累计售出数量 spl_sold 开始，依次用 usd_value 买下各档位的剩余数量，
+// 跨越档位边界的购买在每个档位按该档位的价格计算
+fn tiered_spl_amount(tiers: &[PricingTier], spl_sold: u64, usd_value: u64, spl_precision: u64) -> Result<u64> {
+    let mut amount: u64 = 0;
+    let mut usd_left = usd_value;
+    let mut position = spl_sold; // 当前所处的累计售出位置
+    let mut tier_end: u64 = 0;
+
+    for (i, tier) in tiers.iter().enumerate() {
+        tier_end = tier_end.saturating_add(tier.supply);
+        let is_last = i + 1 == tiers.len();
+        if position >= tier_end && !is_last {
+            continue;
+        }
+
+        let affordable = usd_to_spl_amount(usd_left, spl_precision, tier.price_usd)?;
+        let tier_left = if is_last { u64::MAX } else { tier_end - position };
+        if affordable <= tier_left {
+            return amount.checked_add(affordable).ok_or(CustomError::MathOverflow.into());
+        }
+
+        // 买下该档位剩余的全部数量，花费向上取整，对合约有利
+        let cost = ((tier_left as u128) * (tier.price_usd as u128)).div_ceil(spl_precision as u128) as u64;
+        amount = amount.checked_add(tier_left).ok_or(CustomError::MathOverflow)?;
+        usd_left = usd_left.saturating_sub(cost);
+        position = tier_end;
+    }
+    Ok(amount)
+}
+
+// 定时调价中 now 时刻生效的价格：最后一个 timestamp 不晚于 now 的步骤，第一步之前使用默认价格
+fn scheduled_price(state: &State, now: i64) -> u64 {
+    state.price_steps[..state.price_step_count as usize]
+        .iter()
+        .rev()
+        .find(|step| step.timestamp <= now)
+        .map_or(SPL_PRICE_IN_USD, |step| step.price_usd)
+}
+
+// 荷兰拍在 now 时刻的价格：start_time 之前为起始价格，end_time 之后为最低价格
+fn auction_price(config: &DutchAuctionConfig, now: i64) -> u64 {
+    if now <= config.start_time {
+        return config.start_price;
+    }
+    if now >= config.end_time {
+        return config.floor_price;
+    }
+
+    let range = (config.start_price - config.floor_price) as u128;
+    let elapsed = (now - config.start_time) as u128;
+    let decayed = match config.decay {
+        AuctionDecay::Linear => {
+            let duration = (config.end_time - config.start_time) as u128;
+            range - (range * elapsed) / duration
+        }
+        AuctionDecay::Exponential => {
+            // 整数个半衰期直接右移，不足一个半衰期的部分在两次减半之间线性插值
+            let half_life = config.half_life as u128;
+            let halvings = elapsed / half_life;
+            let remainder = elapsed % half_life;
+            let value = if halvings >= 128 { 0 } else { range >> halvings };
+            value - (value * remainder) / (2 * half_life)
+        }
+    };
+    config.floor_price + (decayed as u64)
+}
+
+// 按最终成交价应付的数量，向上取整，对合约有利
+fn auction_cost(payments: &AuctionPayments, clearing_price: u64) -> u64 {
+    ((payments.weighted * (clearing_price as u128)).div_ceil(AUCTION_WEIGHT_SCALE)) as u64
+}
+
+// 荷兰拍统一结算时，合约需要为尚未领取的差价保留的 SOL / USDC / USDT 数量
+fn auction_rebates_held(state: &State) -> (u64, u64, u64) {
+    if state.pricing_mode != PricingMode::DutchAuction || !state.dutch_auction.uniform_clearing {
+        return (0, 0, 0);
+    }
+    let totals = &state.auction_totals;
+    let held = |payments: &AuctionPayments| {
+        let cost = ((payments.weighted * (totals.clearing_price as u128)) / AUCTION_WEIGHT_SCALE) as u64;
+        payments.paid.saturating_sub(cost).saturating_sub(payments.rebated)
+    };
+    (held(&totals.sol), held(&totals.usdc), held(&totals.usdt))
+}
+
+// 荷兰拍统一结算时记录本次付款和成交价格，其它定价方式下不做任何事
+fn record_auction_purchase(
+    state: &mut State,
+    auction_bid: Option<&mut AuctionBid>,
+    owner: Pubkey,
+    asset: PaymentAsset,
+    payment_amount: u64
+) -> Result<()> {
+    if state.pricing_mode != PricingMode::DutchAuction || !state.dutch_auction.uniform_clearing {
+        return Ok(());
+    }
+    let auction_bid = auction_bid.ok_or(CustomError::AuctionBidRequired)?;
+    let price = auction_price(&state.dutch_auction, Clock::get()?.unix_timestamp);
+    let weighted = ((payment_amount as u128) * AUCTION_WEIGHT_SCALE).div_ceil(price as u128);
+
+    let totals = &mut state.auction_totals;
+    if totals.clearing_price == 0 || price < totals.clearing_price {
+        totals.clearing_price = price;
+    }
+
+    auction_bid.owner = owner;
+    let (bid_payments, total_payments) = match asset {
+        PaymentAsset::Sol => (&mut auction_bid.sol, &mut totals.sol),
+        PaymentAsset::Usdc => (&mut auction_bid.usdc, &mut totals.usdc),
+        PaymentAsset::Usdt => (&mut auction_bid.usdt, &mut totals.usdt),
+    };
+    for payments in [bid_payments, total_payments] {
+        payments.paid = payments.paid.checked_add(payment_amount).ok_or(CustomError::MathOverflow)?;
+        payments.weighted = payments.weighted.checked_add(weighted).ok_or(CustomError::MathOverflow)?;
+    }
+    Ok(())
+}
+
+// 联合曲线从累计售出 0 积分到 x 的结果，返回 (分子, 分母)，单位为 micro-USD * SCY 最小单位
+fn curve_integral(curve: &BondingCurveConfig, x: u64, spl_precision: u64) -> Result<(u128, u128)> {
+    let (x, base) = (x as u128, curve.base_price as u128);
+    match curve.kind {
+        BondingCurveKind::Linear => {
+            // ∫ base + slope * s / (UNIT * P) ds = base * x + slope * x² / (2 * UNIT * P)
+            let denominator = 2 * CURVE_SLOPE_UNIT * (spl_precision as u128);
+            let numerator = base
+                .checked_mul(x)
+                .and_then(|v| v.checked_mul(denominator))
+                .and_then(|v| v.checked_add((curve.slope_per_million as u128).checked_mul(x.checked_mul(x)?)?))
+                .ok_or(CustomError::MathOverflow)?;
+            Ok((numerator, denominator))
+        }
+        BondingCurveKind::Exponential => {
+            // 第 k 段 [kD, (k+1)D] 的价格从 base * 2^k 线性涨到 base * 2^(k+1)，面积为 1.5 * base * 2^k * D；
+            // 整段之和为 1.5 * base * D * (2^n - 1)，再加上第 n 段中 r = x - nD 部分的面积，整体乘以 2D 保持整数
+            let d = curve.doubling_supply as u128;
+            let (n, r) = (x / d, x % d);
+            let power = (1u128).checked_shl(n as u32).filter(|_| n < 128).ok_or(CustomError::MathOverflow)?;
+            let full = base
+                .checked_mul(3)
+                .and_then(|v| v.checked_mul(d.checked_mul(d)?))
+                .and_then(|v| v.checked_mul(power - 1))
+                .ok_or(CustomError::MathOverflow)?;
+            let partial = base
+                .checked_mul(power)
+                .and_then(|v| v.checked_mul((2 * d).checked_mul(r)?.checked_add(r.checked_mul(r)?)?))
+                .ok_or(CustomError::MathOverflow)?;
+            Ok((full.checked_add(partial).ok_or(CustomError::MathOverflow)?, 2 * d))
+        }
+    }
+}
+
+// 按联合曲线购买 [spl_sold, spl_sold + amount) 这一段 SCY 的花费（micro-USD），向上取整，对合约有利
+// 返回 u128，超过 u64 的花费由调用方与预算比较，不视为溢出
+fn curve_cost(curve: &BondingCurveConfig, spl_sold: u64, amount: u64, spl_precision: u64) -> Result<u128> {
+    let end = spl_sold.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    let (from, denominator) = curve_integral(curve, spl_sold, spl_precision)?;
+    let (to, _) = curve_integral(curve, end, spl_precision)?;
+    Ok((to - from).div_ceil(denominator * (spl_precision as u128)))
+}
+
+// 按联合曲线计算 usd_value 可以买到的 SCY：二分查找花费不超过 usd_value 的最大数量
+fn bonding_curve_spl_amount(
+    curve: &BondingCurveConfig,
+    spl_sold: u64,
+    usd_value: u64,
+    spl_precision: u64
+) -> Result<u64> {
+    // 价格随售出递增，按 base_price 计算的数量是上界
+    let (mut low, mut high) = (0u64, usd_to_spl_amount(usd_value, spl_precision, curve.base_price)?);
+
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        // 只有花费超过预算时才缩小上界，计算溢出等错误直接返回，不能当作买不起而少发 SCY
+        if curve_cost(curve, spl_sold, mid, spl_precision)? <= (usd_value as u128) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+// 在按价格计算出的 SCY 数量上加上大额购买奖励：取 usd_value 达到的最高档位
+fn apply_volume_bonus(state: &State, usd_value: u64, spl_amount: u64) -> Result<u64> {
+    let bonus_bps = state.volume_bonuses[..state.volume_bonus_count as usize]
+        .iter()
+        .filter(|tier| usd_value >= tier.min_usd)
+        .map(|tier| tier.bonus_bps)
+        .max()
+        .unwrap_or_default();
+    let bonus = ((spl_amount as u128) * (bonus_bps as u128)) / (BPS_DENOMINATOR as u128);
+    Ok(spl_amount.checked_add(bonus as u64).ok_or(CustomError::MathOverflow)?)
+}
+
+// 计算 usd_value 可以买到的 SCY：指定轮次时按轮次价格，荷兰拍模式下按当前拍卖价格，联合曲线模式下按曲线积分，
+// 配置了价格档位时按档位分段计价，配置了定时调价时按当前生效的价格，否则按默认价格
+fn price_spl_amount(
+    state: &State,
+    round: Option<&SaleRound>,
+    spl_sold: u64,
+    usd_value: u64,
+    spl_precision: u64
+) -> Result<u64> {
+    if let Some(round) = round {
+        return usd_to_spl_amount(usd_value, spl_precision, round.price_usd);
+    }
+    if state.pricing_mode == PricingMode::DutchAuction {
+        let price = auction_price(&state.dutch_auction, Clock::get()?.unix_timestamp);
+        return usd_to_spl_amount(usd_value, spl_precision, price);
+    }
+    if state.pricing_mode == PricingMode::BondingCurve {
+        return bonding_curve_spl_amount(&state.bonding_curve, spl_sold, usd_value, spl_precision);
+    }
+    if state.pricing_tier_count == 0 {
+        let price = scheduled_price(state, Clock::get()?.unix_timestamp);
+        return usd_to_spl_amount(usd_value, spl_precision, price);
+    }
+    let tiers = &state.pricing_tiers[..state.pricing_tier_count as usize];
+    tiered_spl_amount(tiers, spl_sold, usd_value, spl_precision)
+}
+
+// 按硬顶计算本次实际收取的付款数量：未超过硬顶时原样返回；超过时按剩余额度等比例缩减（向下取整，
+// 多出的付款不会被转走），或在 partial_fill_at_cap 为 false 时拒绝购买
+fn cap_payment(state: &State, sale_stats: &SaleStats, payment_amount: u64, usd_value: u64) -> Result<u64> {
+    if state.hard_cap_usd == 0 {
+        return Ok(payment_amount);
+    }
+    let remaining = state.hard_cap_usd.saturating_sub(sale_stats.usd_raised);
+    require!(remaining > 0, CustomError::HardCapReached);
+    if usd_value <= remaining {
+        return Ok(payment_amount);
+    }
+    require!(state.partial_fill_at_cap, CustomError::HardCapExceeded);
+
+    let filled = ((payment_amount as u128) * (remaining as u128)) / (usd_value as u128);
+    require!(filled > 0, CustomError::HardCapReached);
+    Ok(filled as u64)
+}
+
+// 可购买数量不足时按 available 计算需要收取的付款：payment_for 按当前的定价方式（价格档位、联合曲线、奖励等）
+// 计算买到 available 所需的最少付款，不超过原付款数量；未开启 allow_partial 时返回 error
+fn fill_from_inventory(
+    available: u64,
+    payment_amount: u64,
+    spl_amount: u64,
+    allow_partial: bool,
+    error: CustomError,
+    payment_for: impl FnOnce(u64) -> Result<u64>
+) -> Result<u64> {
+    if spl_amount <= available {
+        return Ok(payment_amount);
+    }
+    if !allow_partial || available == 0 {
+        return Err(error.into());
+    }
+    Ok(payment_for(available)?.min(payment_amount))
+}
+
+// pda_spl_ata 中未被 reserved_spl 占用、可以出售或提取的 SCY
+fn available_spl(pda_spl_ata: &TokenAccount, state: &State) -> u64 {
+    pda_spl_ata.amount.saturating_sub(state.reserved_spl)
+}
+
+// 荷兰拍模式下只能在 [start_time, end_time) 内按拍卖价格购买，拍卖开始前和结束后都不能购买
+fn require_auction_open(state: &State) -> Result<()> {
+    if state.pricing_mode != PricingMode::DutchAuction {
+        return Ok(());
+    }
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        now >= state.dutch_auction.start_time && now < state.dutch_auction.end_time,
+        CustomError::AuctionNotActive
+    );
+    Ok(())
+}
+
+// 指定轮次时校验该轮次是否正在进行、用户是否在白名单中，返回该轮次（不指定轮次时为 None）
+fn active_round(
+    state: &State,
+    round_index: Option<u8>,
+    sale_round: Option<&SaleRound>,
+    allowlisted: bool
+) -> Result<Option<SaleRound>> {
+    let Some(index) = round_index else {
+        // 开启 round_required 时必须指定轮次，不能绕过私募轮次的白名单、额度和释放规则按默认价格购买
+        require!(!state.round_required, CustomError::RoundIndexRequired);
+        require_auction_open(state)?;
+        return Ok(None);
+    };
+    // 软顶退款只处理 Allocation，轮次购买不能与软顶模式同时使用
+    require!(state.soft_cap_usd == 0, CustomError::RoundsUnavailableWithSoftCap);
+    let round = sale_round.ok_or(CustomError::SaleRoundRequired)?;
+    require!(round.index == index, CustomError::InvalidSaleRound);
+
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= round.start_time && now < round.end_time, CustomError::RoundNotActive);
+    require!(!round.allowlist_required || allowlisted, CustomError::NotAllowlisted);
+    Ok(Some(round.clone()))
+}
+
+// 开启 Merkle 白名单时校验买家的 proof，返回该钱包还可以购买的 SCY；未开启时返回 None
+fn allowlist_remaining(
+    state: &State,
+    buyer: Pubkey,
+    buyer_info: &BuyerInfo,
+    allowlist_proof: Option<&AllowlistProof>
+) -> Result<Option<u64>> {
+    if state.merkle_root == [0u8; 32] {
+        return Ok(None);
+    }
+    let allowlist_proof = allowlist_proof.ok_or(CustomError::MerkleProofRequired)?;
+    let leaf = merkle::leaf_hash(&buyer, allowlist_proof.max_allocation);
+    require!(
+        merkle::verify(&state.merkle_root, leaf, &allowlist_proof.proof),
+        CustomError::InvalidMerkleProof
+    );
+    Ok(Some(allowlist_proof.max_allocation.saturating_sub(buyer_info.spl_purchased)))
+}
+
+// 在同一笔交易中查找 Ed25519 签名验证指令，确认其中包含 signer 对 message 的签名
+// 签名本身由 Ed25519 原生程序验证（验证失败整笔交易失败），这里只需要核对公钥和消息内容
+fn has_ed25519_signature(instructions_sysvar: &AccountInfo, signer: &Pubkey, message: &[u8]) -> Result<bool> {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    let read_u16 = |data: &[u8], at: usize| -> Option<usize> {
+        data.get(at..at + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+
+    let mut index = 0;
+    while let Ok(instruction) = sysvar_instructions::load_instruction_at_checked(index, instructions_sysvar) {
+        index += 1;
+        if instruction.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        let data = &instruction.data;
+        let signature_count = data.first().copied().unwrap_or_default() as usize;
+        for i in 0..signature_count {
+            let at = HEADER_LEN + i * OFFSETS_LEN;
+            let (Some(public_key_offset), Some(public_key_index), Some(message_offset), Some(message_size), Some(message_index)) = (
+                read_u16(data, at + 4),
+                read_u16(data, at + 6),
+                read_u16(data, at + 8),
+                read_u16(data, at + 10),
+                read_u16(data, at + 12),
+            ) else {
+                continue;
+            };
+            // 公钥和消息必须位于签名验证指令自身的数据中（instruction_index 为 u16::MAX）
+            if public_key_index != u16::MAX as usize || message_index != u16::MAX as usize {
+                continue;
+            }
+            let signed_key = data.get(public_key_offset..public_key_offset + 32);
+            let signed_message = data.get(message_offset..message_offset + message_size);
+            if signed_key == Some(signer.as_ref()) && signed_message == Some(message) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+// 开启 KYC 时校验 attester 的签名证明，返回该钱包在签名等级下还可以购买的 SCY；未开启时返回 None
+fn kyc_remaining(
+    state: &State,
+    instructions_sysvar: &AccountInfo,
+    buyer: Pubkey,
+    buyer_info: &BuyerInfo,
+    attestation: Option<&KycAttestation>
+) -> Result<Option<u64>> {
+    if state.kyc_attester == Pubkey::default() {
+        return Ok(None);
+    }
+    let attestation = attestation.ok_or(CustomError::KycAttestationRequired)?;
+    require!(attestation.expiry > Clock::get()?.unix_timestamp, CustomError::KycAttestationExpired);
+    require!((attestation.tier as usize) < (state.kyc_tier_count as usize), CustomError::InvalidKycTier);
+    require!(
+        has_ed25519_signature(instructions_sysvar, &state.kyc_attester, &attestation.message(&buyer))?,
+        CustomError::InvalidKycAttestation
+    );
+
+    let tier_limit = state.kyc_tier_limits[attestation.tier as usize];
+    Ok(Some(tier_limit.saturating_sub(buyer_info.spl_purchased)))
+}
+
+// 轮次开启持币门槛时校验用户持有的合作方代币，余额需达到 gating_min_balance
+// gating_allocation_rate 不为 0 时，钱包在该轮次累计可购买的 SCY = 余额 * rate / GATING_RATE_SCALE，返回剩余额度
+// 余额在购买时读取而不是快照，代币可以在钱包之间转移后重复使用；需要按某一时刻的持仓分配额度时，
+// 应按快照生成 Merkle 白名单（set_merkle_root），而不是使用持币门槛
+fn gating_remaining(
+    round: Option<&SaleRound>,
+    buyer: Pubkey,
+    round_purchase: Option<&RoundPurchase>,
+    gating_token_account: Option<&TokenAccount>
+) -> Result<Option<u64>> {
+    let Some(round) = round.filter(|round| round.gating_mint != Pubkey::default()) else {
+        return Ok(None);
+    };
+    let gating_token_account = gating_token_account.ok_or(CustomError::GatingTokenAccountRequired)?;
+    require_keys_eq!(gating_token_account.mint, round.gating_mint, CustomError::InvalidGatingTokenAccount);
+    require_keys_eq!(gating_token_account.owner, buyer, CustomError::InvalidGatingTokenAccount);
+    require!(gating_token_account.amount >= round.gating_min_balance, CustomError::InsufficientGatingBalance);
+
+    if round.gating_allocation_rate == 0 {
+        return Ok(None);
+    }
+    let allocation = (gating_token_account.amount as u128)
+        .checked_mul(round.gating_allocation_rate as u128)
+        .ok_or(CustomError::MathOverflow)?
+        / GATING_RATE_SCALE;
+    let allocation = u64::try_from(allocation).unwrap_or(u64::MAX);
+    let purchased = round_purchase.map_or(0, |round_purchase| round_purchase.purchased);
+    Ok(Some(allocation.saturating_sub(purchased)))
+}
+
+// 本次购买最多可以成交的 SCY 数量，以及超出时返回的错误：库存、轮次剩余额度和钱包上限，
+// 以及 wallet_limits 中开启的钱包累计额度（白名单、KYC 等级、持币门槛）中最小的一个
+fn purchase_limit(
+    available: u64,
+    round: Option<&SaleRound>,
+    round_purchase: Option<&RoundPurchase>,
+    wallet_limits: &[(Option<u64>, CustomError)]
+) -> Result<(u64, CustomError)> {
+    let mut limit = (available, CustomError::InsufficientSPLBalance);
+    if let Some(round) = round {
+        let round_purchase = round_purchase.ok_or(CustomError::RoundPurchaseRequired)?;
+        let mut round_left = round.allocation.saturating_sub(round.sold);
+        if round.per_wallet_cap > 0 {
+            round_left = round_left.min(round.per_wallet_cap.saturating_sub(round_purchase.purchased));
+        }
+        if round_left < limit.0 {
+            limit = (round_left, CustomError::RoundLimitExceeded);
+        }
+    }
+    for &(wallet_left, error) in wallet_limits {
+        if let Some(wallet_left) = wallet_left {
+            if wallet_left < limit.0 {
+                limit = (wallet_left, error);
+            }
+        }
+    }
+    Ok(limit)
+}
+
+// 轮次购买的 SCY 记入用户在该轮次的购买记录，并从可售库存中预留出来
+fn credit_round(
+    sale_round: &mut SaleRound,
+    round_purchase: &mut RoundPurchase,
+    state: &mut State,
+    owner: Pubkey,
+    amount: u64
+) -> Result<()> {
+    round_purchase.owner = owner;
+    round_purchase.round_index = sale_round.index;
+    round_purchase.purchased = round_purchase.purchased.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    sale_round.sold = sale_round.sold.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    state.reserved_spl = state.reserved_spl.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
+
+// 校验释放规则：TGE 释放比例不超过 100%，时长不能为负
+fn is_valid_vesting_schedule(schedule: &VestingSchedule) -> bool {
+    (schedule.tge_unlock_bps as u64) <= BPS_DENOMINATOR &&
+        schedule.cliff_duration >= 0 &&
+        schedule.vesting_duration >= 0
+}
+
+// 由 state PDA 签名，从 pda_spl_ata 向 to 转出 SCY
+fn transfer_spl_from_pda<'info>(
+    token_program: &Program<'info, Token>,
+    pda_spl_ata: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    state: &Account<'info, State>,
+    state_bump: u8,
+    amount: u64
+) -> Result<()> {
+    let seeds = &[b"state".as_ref(), &[state_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        SplTransfer {
+            from: pda_spl_ata.to_account_info(),
+            to: to.to_account_info(),
+            authority: state.to_account_info(),
+        },
+        signer
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+// 把购买的 SCY 记入用户的锁仓记录，并从可售库存中预留出来
+fn credit_vesting(position: &mut VestingPosition, state: &mut State, owner: Pubkey, amount: u64) -> Result<()> {
+    if position.owner == Pubkey::default() {
+        position.owner = owner;
+        position.schedule = state.vesting_schedule;
+    } else {
+        // 释放规则在第一笔锁仓购买后冻结，已有的锁仓记录与当前规则始终一致
+        require!(position.schedule == state.vesting_schedule, CustomError::VestingScheduleMismatch);
+    }
+    state.vesting_schedule_locked = true;
+
+    position.total_amount = position.total_amount.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    state.reserved_spl = state.reserved_spl.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
+
+// 把购买的 SCY 记入用户的待领取额度，并从可售库存中预留出来
+fn credit_allocation(allocation: &mut Allocation, state: &mut State, owner: Pubkey, amount: u64) -> Result<()> {
+    allocation.owner = owner;
+    allocation.amount = allocation.amount.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    state.reserved_spl = state.reserved_spl.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
+
+// 按 state.delivery_mode 发放本次购买的 SCY：立即转给用户，或记入用户的锁仓记录 / 待领取额度
+#[allow(clippy::too_many_arguments)]
+fn deliver_spl<'info>(
+    state: &mut Account<'info, State>,
+    state_bump: u8,
+    token_program: &Program<'info, Token>,
+    pda_spl_ata: &Account<'info, TokenAccount>,
+    user_spl_ata: &Account<'info, TokenAccount>,
+    vesting_position: Option<&mut VestingPosition>,
+    allocation: Option<&mut Allocation>,
+    owner: Pubkey,
+    amount: u64
+) -> Result<()> {
+    match state.delivery_mode {
+        DeliveryMode::Immediate => {
+            transfer_spl_from_pda(token_program, pda_spl_ata, user_spl_ata, state, state_bump, amount)
+        }
+        DeliveryMode::Vesting => {
+            let position = vesting_position.ok_or(CustomError::VestingPositionRequired)?;
+            credit_vesting(position, state, owner, amount)
+        }
+        DeliveryMode::DeferredClaim => {
+            let allocation = allocation.ok_or(CustomError::AllocationRequired)?;
+            credit_allocation(allocation, state, owner, amount)
+        }
+    }
+}
+
+// 记录推荐人的统计数据，按 referral_reward_bps 计算奖励并从剩余库存中预留出来（库存不足时只预留剩余部分）
+fn accrue_referral(
+    state: &mut State,
+    referrer: &mut Referrer,
+    buyer: Pubkey,
+    recipient: Pubkey,
+    remaining_spl: u64,
+    spl_amount: u64,
+    usd_value: u64
+) -> Result<()> {
+    // 付款方和接收方都不能是推荐人自己，否则推荐人可以代自己购买来领取奖励
+    require_keys_neq!(referrer.owner, buyer, CustomError::SelfReferral);
+    require_keys_neq!(referrer.owner, recipient, CustomError::SelfReferral);
+
+    let reward = ((spl_amount as u128) * (state.referral_reward_bps as u128)) / (BPS_DENOMINATOR as u128);
+    let reward = (reward as u64).min(remaining_spl);
+
+    referrer.referred_purchases = referrer.referred_purchases.checked_add(1).ok_or(CustomError::MathOverflow)?;
+    referrer.referred_spl = referrer.referred_spl.checked_add(spl_amount).ok_or(CustomError::MathOverflow)?;
+    referrer.referred_usd = referrer.referred_usd.checked_add(usd_value).ok_or(CustomError::MathOverflow)?;
+    referrer.pending_rewards = referrer.pending_rewards.checked_add(reward).ok_or(CustomError::MathOverflow)?;
+    state.reserved_spl = state.reserved_spl.checked_add(reward).ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
+
+// 校验优惠码明文与账户中的哈希一致、未过期且仍有剩余次数，返回优惠码的奖励比例（基点）；未使用优惠码时返回 0
+fn promo_bonus_bps(promo: Option<&PromoCode>, code: Option<&str>) -> Result<u16> {
+    let Some(promo) = promo else {
+        require!(code.is_none(), CustomError::PromoCodeRequired);
+        return Ok(0);
+    };
+    let code = code.ok_or(CustomError::PromoCodeRequired)?;
+    require!(hash(code.as_bytes()).to_bytes() == promo.code_hash, CustomError::InvalidPromoCode);
+    require!(Clock::get()?.unix_timestamp < promo.expiry, CustomError::PromoCodeExpired);
+    require!(promo.remaining_uses > 0, CustomError::PromoCodeExhausted);
+    Ok(promo.bonus_bps)
+}
+
+// 按优惠码的奖励比例在 spl_amount（已含大额购买奖励）之上额外发放 SCY
+fn apply_promo_bonus(bonus_bps: u16, spl_amount: u64) -> Result<u64> {
+    let bonus = ((spl_amount as u128) * (bonus_bps as u128)) / (BPS_DENOMINATOR as u128);
+    Ok(spl_amount.checked_add(bonus as u64).ok_or(CustomError::MathOverflow)?)
+}
+
+// 批量购买只支持直接发放到 ATA、且没有钱包级限制的销售配置：
+// 锁仓 / TGE 后领取、软顶退款、荷兰拍统一结算，以及白名单、KYC 这类钱包级限制都需要买家自己的账户（持币门槛只用于轮次购买）
+fn require_batch_available(state: &State) -> Result<()> {
+    require!(
+        state.delivery_mode == DeliveryMode::Immediate &&
+            state.soft_cap_usd == 0 &&
+            !(state.pricing_mode == PricingMode::DutchAuction && state.dutch_auction.uniform_clearing) &&
+            state.merkle_root == [0u8; 32] &&
+            state.kyc_attester == Pubkey::default(),
+        CustomError::BatchPurchaseUnavailable
+    );
+    Ok(())
+}
+
+// 批量购买时加载接收人的 BuyerInfo（地址为 [b"buyer", recipient]），不存在时由付款方付费创建
+fn load_buyer_info<'info>(
+    buyer_info: &'info AccountInfo<'info>,
+    recipient: Pubkey,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>
+) -> Result<Account<'info, BuyerInfo>> {
+    let (address, bump) = Pubkey::find_program_address(&[b"buyer", recipient.as_ref()], &crate::ID);
+    require_keys_eq!(address, buyer_info.key(), CustomError::InvalidBatchRecipient);
+    if !buyer_info.data_is_empty() {
+        return Account::try_from(buyer_info);
+    }
+
+    let space = 8 + BuyerInfo::LEN;
+    let rent = Rent::get()?.minimum_balance(space);
+    let seeds: &[&[u8]] = &[b"buyer", recipient.as_ref(), &[bump]];
+    let lamports = buyer_info.lamports();
+    if lamports == 0 {
+        anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::CreateAccount { from: payer.to_account_info(), to: buyer_info.clone() },
+                &[seeds]
+            ),
+            rent,
+            space as u64,
+            &crate::ID
+        )?;
+    } else {
+        // 与 Anchor 的 init 相同：地址上已有 lamports 时补足租金再 allocate + assign，提前转账不能阻止创建
+        if rent > lamports {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(system_program.to_account_info(), anchor_lang::system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: buyer_info.clone(),
+                }),
+                rent - lamports
+            )?;
+        }
+        anchor_lang::system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Allocate { account_to_allocate: buyer_info.clone() },
+                &[seeds]
+            ),
+            space as u64
+        )?;
+        anchor_lang::system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                anchor_lang::system_program::Assign { account_to_assign: buyer_info.clone() },
+                &[seeds]
+            ),
+            &crate::ID
+        )?;
+    }
+    // 新建的账户数据全为 0，跳过 discriminator 检查，exit 时写入
+    Account::try_from_unchecked(buyer_info)
+}
+
+// 找到可以买到 spl_amount 的最小 USD 金额（micro-USD）：spl_for 随 USD 单调不减，先倍增找到上界再二分
+fn usd_for_spl_amount(spl_amount: u64, spl_for: impl Fn(u64) -> Result<u64>) -> Result<u64> {
+    let mut high: u64 = 1;
+    while spl_for(high)? < spl_amount {
+        high = high.checked_mul(2).ok_or(CustomError::MathOverflow)?;
+    }
+    let mut low = 0;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if spl_for(mid)? >= spl_amount {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(high)
+}
+
+// 拒绝名单 PDA 由本程序创建，账户存在即拒绝该钱包购买
+fn require_not_denied(denied: &AccountInfo) -> Result<()> {
+    let blocked = denied.owner == &crate::ID && !denied.data_is_empty();
+    require!(!blocked, CustomError::WalletBlocked);
+    Ok(())
+}
+
+// 软顶模式下只能在销售进行中且 sale_end 之前购买
+fn require_sale_open(state: &State) -> Result<()> {
+    if state.soft_cap_usd == 0 {
+        return Ok(());
+    }
+    require!(
+        state.sale_status == SaleStatus::Active && Clock::get()?.unix_timestamp < state.sale_end,
+        CustomError::SaleClosed
+    );
+    Ok(())
+}
+
+// 软顶模式下把本次付款记入用户的托管记录
+fn record_escrow(escrow: Option<&mut Escrow>, state: &State, owner: Pubkey, asset: PaymentAsset, amount: u64) -> Result<()> {
+    if state.soft_cap_usd == 0 {
+        return Ok(());
+    }
+    let escrow = escrow.ok_or(CustomError::EscrowRequired)?;
+    escrow.owner = owner;
+    let paid = match asset {
+        PaymentAsset::Sol => &mut escrow.lamports,
+        PaymentAsset::Usdc => &mut escrow.usdc_amount,
+        PaymentAsset::Usdt => &mut escrow.usdt_amount,
+    };
+    *paid = paid.checked_add(amount).ok_or(CustomError::MathOverflow)?;
+    Ok(())
+}
+
+// 由 state PDA 签名，把 from 中的 USDC/USDT 退回给用户
+fn refund_token<'info>(
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    to: Option<&Account<'info, TokenAccount>>,
+    state: &Account<'info, State>,
+    state_bump: u8,
+    amount: u64
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let to = to.ok_or(CustomError::RefundAccountRequired)?;
+    let seeds = &[b"state".as_ref(), &[state_bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        SplTransfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: state.to_account_info(),
+        },
+        signer
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+// 按释放规则计算 total_amount 中截至 now 已释放的 SCY 数量（含已领取部分），TGE 未设置或未到时为 0
+fn vested_amount(total_amount: u64, schedule: &VestingSchedule, tge_timestamp: i64, now: i64) -> u64 {
+    if tge_timestamp == 0 || now < tge_timestamp {
+        return 0;
+    }
+
+    let total = total_amount as u128;
+    let tge_amount = (total * (schedule.tge_unlock_bps as u128)) / (BPS_DENOMINATOR as u128);
+    let cliff_end = tge_timestamp.saturating_add(schedule.cliff_duration);
+    if now < cliff_end {
+        return tge_amount as u64;
+    }
+
+    let elapsed = now - cliff_end;
+    if schedule.vesting_duration == 0 || elapsed >= schedule.vesting_duration {
+        return total_amount;
+    }
+
+    let linear_amount = ((total - tge_amount) * (elapsed as u128)) / (schedule.vesting_duration as u128);
+    (tge_amount + linear_amount) as u64
+}
+
+// 写入成交回执，index 为买家本次购买前的购买次数
+#[allow(clippy::too_many_arguments)]
+fn write_receipt(
+    receipt: &mut Receipt,
+    buyer_info: &BuyerInfo,
+    buyer: Pubkey,
+    payer: Pubkey,
+    payment_mint: Pubkey,
+    payment_amount: u64,
+    spl_amount: u64,
+    price: &Price,
+    client_ref: Option<[u8; 32]>
+) -> Result<()> {
+    receipt.buyer = buyer;
+    receipt.index = buyer_info.purchase_count;
+    receipt.payment_mint = payment_mint;
+    receipt.payment_amount = payment_amount;
+    receipt.spl_amount = spl_amount;
+    receipt.price = price.price;
+    receipt.exponent = price.exponent;
+    receipt.timestamp = Clock::get()?.unix_timestamp;
+    receipt.client_ref = client_ref;
+    receipt.payer = payer;
+    Ok(())
+}
+
+// 传入 client_order_id 时写入新建的订单记录；订单号和订单账户必须同时传入
+fn record_order(
+    order: Option<&mut Order>,
+    client_order_id: Option<[u8; 32]>,
+    buyer: Pubkey,
+    payment_mint: Pubkey,
+    payment_amount: u64,
+    spl_amount: u64
+) -> Result<()> {
+    match (order, client_order_id) {
+        (Some(order), Some(client_order_id)) => {
+            order.buyer = buyer;
+            order.client_order_id = client_order_id;
+            order.payment_mint = payment_mint;
+            order.payment_amount = payment_amount;
+            order.spl_amount = spl_amount;
+            order.timestamp = Clock::get()?.unix_timestamp;
+            Ok(())
+        }
+        (None, None) => Ok(()),
+        _ => Err(CustomError::OrderAccountRequired.into()),
+    }
+}
+
+// 记录一次购买：更新全局销售统计和买家的累计购买记录（各支付资产的原始数量由调用方更新）
+fn record_purchase(
+    sale_stats: &mut SaleStats,
+    buyer_info: &mut BuyerInfo,
+    buyer: Pubkey,
+    spl_amount: u64,
+    usd_value: u64
+) -> Result<()> {
+    if buyer_info.purchase_count == 0 {
+        buyer_info.wallet = buyer;
+        sale_stats.buyer_count = sale_stats.buyer_count.checked_add(1).ok_or(CustomError::MathOverflow)?;
+    }
+    buyer_info.spl_purchased = buyer_info.spl_purchased
+        .checked_add(spl_amount)
+        .ok_or(CustomError::MathOverflow)?;
+    buyer_info.usd_spent = buyer_info.usd_spent.checked_add(usd_value).ok_or(CustomError::MathOverflow)?;
+    buyer_info.purchase_count = buyer_info.purchase_count.checked_add(1).ok_or(CustomError::MathOverflow)?;
+
+    sale_stats.spl_sold = sale_stats.spl_sold.checked_add(spl_amount).ok_or(CustomError::MathOverflow)?;
+    sale_stats.usd_raised = sale_stats.usd_raised.checked_add(usd_value).ok_or(CustomError::MathOverflow)?;
+    sale_stats.last_purchase_slot = Clock::get()?.slot;
+    Ok(())
+}
+
+// 从 PriceUpdateV2 读取 spot 价格，并与同一账户中的 EMA 价格比较，偏离过大时拒绝，防止短时价格操纵
+// 价格过期时返回 None，由调用方决定是否还有其它价格源可用
+fn get_guarded_price(price_update: &PriceUpdateV2, feed_id: &[u8; 32], state: &State) -> Result<Option<i64>> {
+    let spot = match price_update.get_price_no_older_than(&Clock::get()?, MAXIMUM_PRICE_AGE, feed_id) {
+        Ok(price) => price,
+        Err(GetPriceError::PriceTooOld) => {
+            return Ok(None);
+        }
+        Err(err) => {
+            return Err(err.into());
+        }
+    };
+    let ema_price = price_update.price_message.ema_price; // EMA 价格与 spot 价格使用同一个 exponent
+
+    require!(spot.price > 0 && ema_price > 0, CustomError::InvalidPrice);
+    require!(
+        deviation_bps(spot.price, ema_price) <= (state.max_price_deviation_bps as u128),
+        CustomError::PriceDeviationTooHigh
+    );
+
+    // 用户支付的资产价格越低，获得的 SCY 越少，因此较低的价格对合约更有利
+    let price = if state.use_conservative_price { spot.price.min(ema_price) } else { spot.price };
+    Ok(Some(normalize_price(price, spot.exponent)?))
+}
+
+// 读取一个 Pyth 价格源，未过期时记入 prices
+// 同一份签名的价格更新可以被写入任意多个接收账户，因此按 publish_time 去重（feed 已由 get_guarded_price 校验），
+// 否则一份报价复制到多个账户就能凑够 min_price_sources
+fn push_pyth_price(
+    update: &PriceUpdateV2,
+    feed_id: &[u8; 32],
+    state: &State,
+    prices: &mut Vec<i64>,
+    publish_times: &mut Vec<i64>
+) -> Result<()> {
+    let price = get_guarded_price(update, feed_id, state)?;
+    let publish_time = update.price_message.publish_time;
+    require!(!publish_times.contains(&publish_time), CustomError::DuplicatePriceSource);
+    publish_times.push(publish_time);
+
+    if let Some(price) = price {
+        prices.push(price);
+    }
+    Ok(())
+}
+
+// 汇总 price_update 与 remaining_accounts 中额外传入的价格源（Pyth 价格账户或 FallbackPrice 账户），
+// 过滤掉过期的价格后取中位数，并要求与中位数一致的价格源数量不少于 state.min_price_sources
+// price_update 过期时只是不计入，其余未过期的价格源仍然可以满足 min_price_sources
+fn get_median_price(
+    price_update: &Account<PriceUpdateV2>,
+    extra_sources: &[AccountInfo],
+    feed_id: &[u8; 32],
+    state: &State
+) -> Result<Option<Price>> {
+    require!(extra_sources.len() < MAX_PRICE_SOURCES, CustomError::TooManyPriceSources);
+    let now = Clock::get()?.unix_timestamp;
+
+    let mut prices: Vec<i64> = Vec::with_capacity(MAX_PRICE_SOURCES);
+    let mut publish_times: Vec<i64> = Vec::with_capacity(MAX_PRICE_SOURCES);
+    push_pyth_price(price_update, feed_id, state, &mut prices, &mut publish_times)?;
+
+    for (i, source) in extra_sources.iter().enumerate() {
+        // 同一个价格源不能被重复计入
+        require!(
+            source.key() != price_update.key() &&
+                extra_sources[..i].iter().all(|other| other.key != source.key),
+            CustomError::DuplicatePriceSource
+        );
+
+        let data = source.try_borrow_data()?;
+        if *source.owner == pyth_solana_receiver_sdk::ID {
+            let update = PriceUpdateV2::try_deserialize(&mut &data[..])?;
+            push_pyth_price(&update, feed_id, state, &mut prices, &mut publish_times)?;
+        } else if *source.owner == crate::ID {
+            // FallbackPrice 是按 feed_id 生成的 PDA，每个 feed 只有一个，按账户地址去重即可
+            let fallback = FallbackPrice::try_deserialize(&mut &data[..])?;
+            require!(fallback.feed_id == *feed_id, CustomError::InvalidPriceSource);
+            if fallback.updated_at.saturating_add(MAXIMUM_FALLBACK_PRICE_AGE) >= now {
+                prices.push(normalize_price(fallback.price, fallback.exponent)?);
+            }
+        } else {
+            return Err(CustomError::InvalidPriceSource.into());
+        }
+    }
+
+    if prices.is_empty() {
+        return Ok(None);
+    }
+    prices.sort_unstable();
+    // 奇数个时两个下标相同；偶数个时取中间两个价格的平均值
+    let (low, high) = (prices[(prices.len() - 1) / 2], prices[prices.len() / 2]);
+    let median = low + (high - low) / 2;
+
+    // 与中位数的偏离不超过 max_price_deviation_bps 的价格源视为一致
+    let agreeing = prices
+        .iter()
+        .filter(|price| deviation_bps(**price, median) <= (state.max_price_deviation_bps as u128))
+        .count();
+    if agreeing < (state.min_price_sources as usize) {
+        return Ok(None);
+    }
+
+    Ok(Some(Price { price: median, conf: 0, exponent: PRICE_EXPONENT, publish_time: now }))
+}
+
+// 获取本次购买使用的价格：优先使用预言机中位数；未过期且一致的价格源不足时，
+// 只有 admin / price_operator 标记了预言机故障（oracle_down）才使用未过期的人工价格，否则拒绝购买
+// 返回值中的 bool 表示是否使用了人工价格
+fn get_purchase_price(
+    price_update: &Account<PriceUpdateV2>,
+    extra_sources: &[AccountInfo],
+    manual_price: Option<&ManualPrice>,
+    feed_id: &[u8; 32],
+    state: &State
+) -> Result<(Price, bool)> {
+    if let Some(price) = get_median_price(price_update, extra_sources, feed_id, state)? {
+        return Ok((price, false));
+    }
+    require!(state.oracle_down, CustomError::NotEnoughPriceSources);
+
+    let manual_price = manual_price.ok_or(CustomError::ManualPriceRequired)?;
+    require!(manual_price.feed_id == *feed_id, CustomError::InvalidPriceSource);
+    require!(Clock::get()?.unix_timestamp < manual_price.expires_at, CustomError::ManualPriceExpired);
+
+    Ok((
+        Price {
+            price: manual_price.price,
+            conf: 0,
+            exponent: manual_price.exponent,
+            publish_time: manual_price.updated_at,
+        },
+        true,
+    ))
+}
+
+// ----------------------------------------------------主体程序----------------------------------------------------
+#[program]
+pub mod scy_transfer {
+    use super::*;
+
+    // 初始化合约的 state 账户（只会被执行一次）
+    pub fn initialize_state(
+        ctx: Context<InitializeState>, // 调用该交易所需的所有账户信息() 使用 InitializeState 结构体中的账户)
+        usdc_mint: Pubkey, // USDC 代币的 Mint 地址
+        usdt_mint: Pubkey, // USDD 代币的 Mint 地址
+        mint: Pubkey // SCY 代币的 Mint 地址
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state; // state 账户是 一个 State 结构体，且可以修改
+        state.admin = *ctx.accounts.admin.key; // 将 admin 账户的 Pubkey 存入 state 账户，作为合约的 初始管理员
+        state.usdc_mint = usdc_mint;
+        state.usdt_mint = usdt_mint;
+        state.mint = mint;
+        state.max_price_deviation_bps = DEFAULT_MAX_PRICE_DEVIATION_BPS;
+        state.use_conservative_price = false;
+        state.min_price_sources = DEFAULT_MIN_PRICE_SOURCES;
+        state.price_operator = *ctx.accounts.admin.key; // 默认由 admin 兼任价格操作员
+        Ok(())
+    }
+
+    // 初始化 pda_sol （用于储存、管理 SOL 的PDA账户） 结构体中会自动init
+    pub fn initialize_pda_sol(ctx: Context<InitializePdaSol>) -> Result<()> {
+        let rent = Rent::get()?; // 获取当前租金
+        let rent_exempt_lamports = rent.minimum_balance(0); // 计算 0 字节账户的租金豁免金额
+        let admin_signer = &ctx.accounts.admin;
+        let system_program = &ctx.accounts.system_program;
+
+        // 构造 SOL 转账指令
+        let transfer_instruction = system_instruction::transfer(
+            ctx.accounts.admin.key,
+            ctx.accounts.pda_sol_account.key,
+            rent_exempt_lamports
+        );
+
+        anchor_lang::solana_program::program::invoke(
+            &transfer_instruction,
+            &[
+                admin_signer.to_account_info(),
+                ctx.accounts.pda_sol_account.to_account_info(),
+                system_program.to_account_info(),
+            ]
+        )?;
+
+        msg!("PDA SOL account initialized: {}", ctx.accounts.pda_sol_account.key());
+        Ok(())
+    }
+
+    // 初始化 pda_spl_ata （用于储存、管理 SCY 的PDA账户） 结构体中会自动init
+    pub fn initialize_pda_spl_ata(ctx: Context<InitializePdaSplAta>) -> Result<()> {
+        msg!("PDA SPL ATA initialized: {}", ctx.accounts.pda_spl_ata.key());
+        Ok(())
+    }
+
+    // 初始化 pda_usdc_ata （用于储存、管理 USDC 的PDA账户） 结构体中会自动init
+    pub fn initialize_pda_usdc_ata(ctx: Context<InitializePdaUsdcAta>) -> Result<()> {
+        msg!("PDA USDC ATA initialized: {}", ctx.accounts.pda_usdc_ata.key());
+        Ok(())
+    }
+
+    // 初始化 pda_usdt_ata （用于储存、管理 USDT 的PDA账户） 结构体中会自动init
+    pub fn initialize_pda_usdt_ata(ctx: Context<InitializePdaUsdtAta>) -> Result<()> {
+        msg!("PDA USDT ATA initialized: {}", ctx.accounts.pda_usdt_ata.key());
+        Ok(())
+    }
+
+    // 初始化全局销售统计账户 sale_stats（只会被执行一次）
+    pub fn initialize_sale_stats(ctx: Context<InitializeSaleStats>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        msg!("Sale stats initialized: {}", ctx.accounts.sale_stats.key());
+        Ok(())
+    }
+
+    // 设置价格档位（按顺序依次售出），传入空列表表示恢复为统一价格
+    pub fn set_pricing_tiers(ctx: Context<UpdateConfig>, tiers: Vec<PricingTier>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            tiers.len() <= MAX_PRICING_TIERS &&
+                tiers.iter().all(|tier| tier.supply > 0 && tier.price_usd > 0),
+            CustomError::InvalidPricingTiers
+        );
+        // 价格档位与定时调价不能同时使用
+        require!(tiers.is_empty() || state.price_step_count == 0, CustomError::PricingModeConflict);
+
+        state.pricing_tiers = [PricingTier::default(); MAX_PRICING_TIERS];
+        state.pricing_tiers[..tiers.len()].copy_from_slice(&tiers);
+        state.pricing_tier_count = tiers.len() as u8;
+        Ok(())
+    }
+
+    // 设置大额购买奖励档位（min_usd 严格递增），传入空列表表示取消
+    pub fn set_volume_bonuses(ctx: Context<UpdateConfig>, tiers: Vec<VolumeBonus>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            tiers.len() <= MAX_VOLUME_BONUS_TIERS &&
+                tiers.iter().all(|tier| tier.bonus_bps > 0 && (tier.bonus_bps as u64) <= BPS_DENOMINATOR) &&
+                tiers.windows(2).all(|pair| pair[0].min_usd < pair[1].min_usd),
+            CustomError::InvalidVolumeBonuses
+        );
+
+        state.volume_bonuses = [VolumeBonus::default(); MAX_VOLUME_BONUS_TIERS];
+        state.volume_bonuses[..tiers.len()].copy_from_slice(&tiers);
+        state.volume_bonus_count = tiers.len() as u8;
+        Ok(())
+    }
+
+    // 设置定时调价（timestamp 严格递增），传入空列表表示取消定时调价
+    pub fn set_price_schedule(ctx: Context<UpdateConfig>, steps: Vec<PriceStep>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            steps.len() <= MAX_PRICE_STEPS &&
+                steps.iter().all(|step| step.price_usd > 0) &&
+                steps.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp),
+            CustomError::InvalidPriceSchedule
+        );
+        require!(steps.is_empty() || state.pricing_tier_count == 0, CustomError::PricingModeConflict);
+
+        state.price_steps = [PriceStep::default(); MAX_PRICE_STEPS];
+        state.price_steps[..steps.len()].copy_from_slice(&steps);
+        state.price_step_count = steps.len() as u8;
+        Ok(())
+    }
+
+    // 配置荷兰拍参数；统一结算的拍卖一旦有成交就不能再修改
+    pub fn set_dutch_auction(ctx: Context<UpdateConfig>, config: DutchAuctionConfig) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            config.floor_price > 0 &&
+                config.start_price >= config.floor_price &&
+                config.start_time < config.end_time &&
+                (config.decay == AuctionDecay::Linear || config.half_life > 0),
+            CustomError::InvalidDutchAuction
+        );
+        require!(state.auction_totals == AuctionTotals::default(), CustomError::AuctionAlreadyStarted);
+
+        state.dutch_auction = config;
+        Ok(())
+    }
+
+    // 设置 KYC attester 公钥和每个等级的累计购买上限，attester 为默认值时关闭 KYC
+    pub fn set_kyc_config(ctx: Context<UpdateConfig>, attester: Pubkey, tier_limits: Vec<u64>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(tier_limits.len() <= MAX_KYC_TIERS, CustomError::InvalidKycConfig);
+        require!(attester == Pubkey::default() || !tier_limits.is_empty(), CustomError::InvalidKycConfig);
+
+        state.kyc_attester = attester;
+        state.kyc_tier_count = tier_limits.len() as u8;
+        state.kyc_tier_limits = [0; MAX_KYC_TIERS];
+        state.kyc_tier_limits[..tier_limits.len()].copy_from_slice(&tier_limits);
+        Ok(())
+    }
+
+    // 配置某个销售轮次的持币门槛：gating_mint 为默认值时关闭；allocation_rate 不为 0 时按持币数量分配每个钱包在该轮次的购买额度
+    pub fn set_token_gate(
+        ctx: Context<SetTokenGate>,
+        round_index: u8,
+        gating_mint: Pubkey,
+        min_balance: u64,
+        allocation_rate: u64
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        let sale_round = &mut ctx.accounts.sale_round;
+        sale_round.gating_mint = gating_mint;
+        sale_round.gating_min_balance = min_balance;
+        sale_round.gating_allocation_rate = allocation_rate;
+        msg!("Token gate of round {} set to {}", round_index, gating_mint);
+        Ok(())
+    }
+
+    // 设置推荐奖励比例（基点），为 0 时推荐只记录统计数据
+    pub fn set_referral_reward(ctx: Context<UpdateConfig>, reward_bps: u16) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!((reward_bps as u64) <= BPS_DENOMINATOR, CustomError::InvalidReferralReward);
+
+        state.referral_reward_bps = reward_bps;
+        Ok(())
+    }
+
+    // 设置白名单 Merkle root，全 0 表示关闭白名单
+    pub fn set_merkle_root(ctx: Context<UpdateConfig>, merkle_root: [u8; 32]) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        state.merkle_root = merkle_root;
+        Ok(())
+    }
+
+    // 配置联合曲线参数
+    pub fn set_bonding_curve(ctx: Context<UpdateConfig>, config: BondingCurveConfig) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            config.base_price > 0 && (config.kind == BondingCurveKind::Linear || config.doubling_supply > 0),
+            CustomError::InvalidBondingCurve
+        );
+
+        state.bonding_curve = config;
+        Ok(())
+    }
+
+    // 切换定价方式；统一结算的荷兰拍一旦有成交就不能再切换，避免差价无法结算
+    pub fn set_pricing_mode(ctx: Context<UpdateConfig>, pricing_mode: PricingMode) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            pricing_mode != PricingMode::DutchAuction || state.dutch_auction.floor_price > 0,
+            CustomError::InvalidDutchAuction
+        );
+        require!(
+            pricing_mode != PricingMode::BondingCurve || state.bonding_curve.base_price > 0,
+            CustomError::InvalidBondingCurve
+        );
+        require!(state.auction_totals == AuctionTotals::default(), CustomError::AuctionAlreadyStarted);
+
+        state.pricing_mode = pricing_mode;
+        Ok(())
+    }
+
+    // 荷兰拍结束后，早期买家按最终成交价领取多付的差价（以原支付资产返还）
+    pub fn claim_auction_rebate(ctx: Context<ClaimAuctionRebate>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.pricing_mode == PricingMode::DutchAuction && state.dutch_auction.uniform_clearing,
+            CustomError::AuctionNotUniform
+        );
+        require!(Clock::get()?.unix_timestamp >= state.dutch_auction.end_time, CustomError::AuctionNotEnded);
+        // 软顶模式下募资失败时买家通过 claim_refund 取回全部付款，不能再额外领取差价
+        require!(
+            state.soft_cap_usd == 0 || state.sale_status == SaleStatus::Succeeded,
+            CustomError::SaleNotSucceeded
+        );
+
+        let clearing_price = state.auction_totals.clearing_price;
+        let bid = &ctx.accounts.auction_bid;
+        let rebate = |payments: &AuctionPayments| payments.paid.saturating_sub(auction_cost(payments, clearing_price));
+        let (sol_rebate, usdc_rebate, usdt_rebate) = (rebate(&bid.sol), rebate(&bid.usdc), rebate(&bid.usdt));
+
+        if sol_rebate > 0 {
+            let transfer_instruction = system_instruction::transfer(
+                &ctx.accounts.pda_sol_account.key(),
+                &ctx.accounts.owner.key(),
+                sol_rebate
+            );
+
+            invoke_signed(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.pda_sol_account.to_account_info(),
+                    ctx.accounts.owner.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[&[b"pda_sol", &[ctx.bumps.pda_sol_account]]]
+            )?;
+        }
+
+        refund_token(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_usdc_ata,
+            ctx.accounts.user_usdc_ata.as_ref(),
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            usdc_rebate
+        )?;
+        refund_token(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_usdt_ata,
+            ctx.accounts.user_usdt_ata.as_ref(),
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            usdt_rebate
+        )?;
+
+        let totals = &mut ctx.accounts.state.auction_totals;
+        totals.sol.rebated = totals.sol.rebated.checked_add(sol_rebate).ok_or(CustomError::MathOverflow)?;
+        totals.usdc.rebated = totals.usdc.rebated.checked_add(usdc_rebate).ok_or(CustomError::MathOverflow)?;
+        totals.usdt.rebated = totals.usdt.rebated.checked_add(usdt_rebate).ok_or(CustomError::MathOverflow)?;
+
+        msg!("Rebated {} lamports, {} USDC, {} USDT at clearing price {}", sol_rebate, usdc_rebate, usdt_rebate, clearing_price);
+        Ok(())
+    }
+
+    // 询价：按当前的定价方式（价格档位、定时调价、荷兰拍或联合曲线）和大额购买奖励，计算 usd_value（micro-USD）可以买到的 SCY，结果通过 return data 返回
+    pub fn quote(ctx: Context<Quote>, usd_value: u64) -> Result<u64> {
+        let spl_precision = (10_u64).pow(ctx.accounts.mint.decimals as u32);
+        let spl_amount = price_spl_amount(&ctx.accounts.state, None, ctx.accounts.sale_stats.spl_sold, usd_value, spl_precision)?;
+        apply_volume_bonus(&ctx.accounts.state, usd_value, spl_amount)
+    }
+
+    // 查询全局销售统计，结果通过 return data 返回
+    pub fn get_stats(ctx: Context<GetStats>) -> Result<SaleStats> {
+        Ok(ctx.accounts.sale_stats.clone().into_inner())
+    }
+
+    // 更新 admin 账户
+    pub fn update_admin(ctx: Context<UpdateAdmin>, new_admin: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.current_admin.key(), CustomError::Unauthorized); // 确保 current_admin 是现任管理员
+
+        state.admin = new_admin; // 更新管理员地址
+        Ok(())
+    }
+
+    // 升级后把旧的 state 账户扩容到当前的 State::LEN，新增的字段填 0，即默认值（价格保护字段按 initialize_state 的默认值写入）；
+    // 已经是最新大小时不做任何修改
+    pub fn migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+        let state = ctx.accounts.state.to_account_info();
+        {
+            // admin 是 State 的第一个字段，旧布局中的位置不变
+            let data = state.try_borrow_data()?;
+            require!(
+                data.len() >= 8 + 32 && data[..8] == <State as anchor_lang::Discriminator>::DISCRIMINATOR,
+                anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch
+            );
+            let admin = Pubkey::try_from(&data[8..8 + 32]).map_err(|_| CustomError::Unauthorized)?;
+            require_keys_eq!(admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        }
+
+        let space = 8 + State::LEN;
+        if state.data_len() >= space {
+            return Ok(());
+        }
+        let legacy = state.data_len() <= 8 + LEGACY_STATE_LEN;
+        let rent = Rent::get()?.minimum_balance(space);
+        let lamports = state.lamports();
+        if rent > lamports {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(ctx.accounts.system_program.to_account_info(), anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.admin.to_account_info(),
+                    to: state.clone(),
+                }),
+                rent - lamports
+            )?;
+        }
+        state.realloc(space, true)?;
+
+        // 最初版本的账户没有价格保护字段，补上 initialize_state 中的默认值；保持为 0 时偏离上限为 0，所有购买都会被拒绝
+        if legacy {
+            let mut data = state.try_borrow_mut_data()?;
+            let mut migrated = State::try_deserialize(&mut &data[..])?;
+            migrated.max_price_deviation_bps = DEFAULT_MAX_PRICE_DEVIATION_BPS;
+            migrated.use_conservative_price = false;
+            migrated.min_price_sources = DEFAULT_MIN_PRICE_SOURCES;
+            migrated.price_operator = migrated.admin; // 默认由 admin 兼任价格操作员
+            migrated.try_serialize(&mut &mut data[..])?;
+        }
+
+        msg!("State migrated to {} bytes", space);
+        Ok(())
+    }
+
+    // 设置价格偏离保护：spot 与 EMA 的最大偏离（基点），以及是否使用二者中对合约更有利的价格
+    pub fn set_price_guard(
+        ctx: Context<UpdateConfig>,
+        max_price_deviation_bps: u16,
+        use_conservative_price: bool
+    ) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            max_price_deviation_bps > 0 && (max_price_deviation_bps as u64) <= BPS_DENOMINATOR,
+            CustomError::InvalidPriceGuard
+        );
+
+        state.max_price_deviation_bps = max_price_deviation_bps;
+        state.use_conservative_price = use_conservative_price;
+        Ok(())
+    }
+
+    // 设置计算价格中位数时至少需要的一致价格源数量
+    pub fn set_min_price_sources(ctx: Context<UpdateConfig>, min_price_sources: u8) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            min_price_sources > 0 && (min_price_sources as usize) <= MAX_PRICE_SOURCES,
+            CustomError::InvalidPriceSourceCount
+        );
+
+        state.min_price_sources = min_price_sources;
+        Ok(())
+    }
+
+    // 标记预言机故障：为 true 时，预言机价格过期或价格源不足的购买改用人工价格；admin 和 price_operator 都可以开启或关闭
+    pub fn set_oracle_down(ctx: Context<SetOracleDown>, oracle_down: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        let operator = ctx.accounts.operator.key();
+        require!(
+            operator == state.admin || operator == state.price_operator,
+            CustomError::Unauthorized
+        );
+
+        state.oracle_down = oracle_down;
+        msg!("Oracle down: {}", oracle_down);
+        Ok(())
+    }
+
+    // 设置 SCY 的发放方式：立即发放、锁仓释放或 TGE 后领取
+    pub fn set_delivery_mode(ctx: Context<UpdateConfig>, delivery_mode: DeliveryMode) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        // 软顶模式下 SCY 必须等募资成功后才能领取
+        require!(
+            state.soft_cap_usd == 0 || delivery_mode == DeliveryMode::DeferredClaim,
+            CustomError::SoftCapRequiresDeferredClaim
+        );
+
+        state.delivery_mode = delivery_mode;
+        Ok(())
+    }
+
+    // 设置累计募资的硬顶（micro-USD，0 表示不限制），以及超过硬顶的购买是部分成交还是直接拒绝
+    pub fn set_hard_cap(ctx: Context<UpdateConfig>, hard_cap_usd: u64, partial_fill_at_cap: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            hard_cap_usd == 0 || state.soft_cap_usd <= hard_cap_usd,
+            CustomError::InvalidHardCap
+        );
+
+        state.hard_cap_usd = hard_cap_usd;
+        state.partial_fill_at_cap = partial_fill_at_cap;
+        Ok(())
+    }
+
+    // 开启软顶模式：付款留在托管中，直到管理员在 sale_end 之后调用 finalize_sale；
+    // 只能在第一笔购买之前设置，SCY 改为 TGE 后领取
+    pub fn set_soft_cap(ctx: Context<SetSoftCap>, soft_cap_usd: u64, sale_end: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(ctx.accounts.sale_stats.buyer_count == 0, CustomError::SaleAlreadyStarted);
+        require!(
+            soft_cap_usd > 0 &&
+                sale_end > Clock::get()?.unix_timestamp &&
+                (state.hard_cap_usd == 0 || soft_cap_usd <= state.hard_cap_usd),
+            CustomError::InvalidSoftCap
+        );
+
+        state.soft_cap_usd = soft_cap_usd;
+        state.sale_end = sale_end;
+        state.sale_status = SaleStatus::Active;
+        state.delivery_mode = DeliveryMode::DeferredClaim;
+        Ok(())
+    }
+
+    // sale_end 之后确定软顶募资结果：达到软顶则资金解锁、SCY 可领取，否则买家可以申请退款
+    // 结果只取决于 sale_end 和累计募资，任何人都可以调用，管理员不调用时买家也不会无法退款
+    pub fn finalize_sale(ctx: Context<FinalizeSale>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require!(state.soft_cap_usd > 0, CustomError::SoftCapNotSet);
+        require!(state.sale_status == SaleStatus::Active, CustomError::SaleAlreadyFinalized);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= state.sale_end, CustomError::SaleNotEnded);
+
+        if ctx.accounts.sale_stats.usd_raised >= state.soft_cap_usd {
+            state.sale_status = SaleStatus::Succeeded;
+            if state.tge_timestamp == 0 {
+                state.tge_timestamp = now; // 未单独设置 TGE 时，募资成功后立即可以领取
+            }
+        } else {
+            state.sale_status = SaleStatus::Failed;
+        }
+
+        msg!("Sale finalized, raised {} of soft cap {}", ctx.accounts.sale_stats.usd_raised, state.soft_cap_usd);
+        Ok(())
+    }
+
+    // 募资失败后，用户按托管记录取回全部付款，并释放为其预留的 SCY
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        require!(ctx.accounts.state.sale_status == SaleStatus::Failed, CustomError::SaleNotFailed);
+
+        let escrow = &ctx.accounts.escrow;
+        let (lamports, usdc_amount, usdt_amount) = (escrow.lamports, escrow.usdc_amount, escrow.usdt_amount);
+
+        if lamports > 0 {
+            let transfer_instruction = system_instruction::transfer(
+                &ctx.accounts.pda_sol_account.key(),
+                &ctx.accounts.owner.key(),
+                lamports
+            );
+
+            invoke_signed(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.pda_sol_account.to_account_info(),
+                    ctx.accounts.owner.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[&[b"pda_sol", &[ctx.bumps.pda_sol_account]]]
+            )?;
+        }
+
+        refund_token(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_usdc_ata,
+            ctx.accounts.user_usdc_ata.as_ref(),
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            usdc_amount
+        )?;
+        refund_token(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_usdt_ata,
+            ctx.accounts.user_usdt_ata.as_ref(),
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            usdt_amount
+        )?;
+
+        let allocation = &ctx.accounts.allocation;
+        let unclaimed = allocation.amount.saturating_sub(allocation.claimed_amount);
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(unclaimed);
+
+        msg!("Refunded {} lamports, {} USDC, {} USDT", lamports, usdc_amount, usdt_amount);
+        Ok(())
+    }
+
+    // 设置 TGE 时间；TGE 一旦到达就不能再修改，避免已开始的领取被推迟
+    pub fn set_tge_timestamp(ctx: Context<UpdateConfig>, tge_timestamp: i64) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(state.tge_timestamp == 0 || now < state.tge_timestamp, CustomError::TgeAlreadyStarted);
+        require!(tge_timestamp > 0, CustomError::InvalidTgeTimestamp);
+
+        state.tge_timestamp = tge_timestamp;
+        Ok(())
+    }
+
+    // 设置锁仓模式下的释放规则，从 TGE 开始计算；第一笔锁仓购买之后不能再修改，避免老买家无法继续购买
+    pub fn set_vesting_schedule(ctx: Context<UpdateConfig>, vesting_schedule: VestingSchedule) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(!state.vesting_schedule_locked, CustomError::VestingScheduleLocked);
+        require!(is_valid_vesting_schedule(&vesting_schedule), CustomError::InvalidVestingSchedule);
+
+        state.vesting_schedule = vesting_schedule;
+        Ok(())
+    }
+
+    // 用户领取锁仓记录中已释放的 SCY
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let position = &mut ctx.accounts.vesting_position;
+        let claimable = vested_amount(
+            position.total_amount,
+            &position.schedule,
+            ctx.accounts.state.tge_timestamp,
+            now
+        ).saturating_sub(position.claimed_amount);
+        require!(claimable > 0, CustomError::NothingToClaim);
+
+        position.claimed_amount += claimable;
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(claimable);
+
+        transfer_spl_from_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_spl_ata,
+            &ctx.accounts.user_spl_ata,
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            claimable
+        )?;
+
+        msg!("Claimed {} vested SCY", claimable);
+        Ok(())
+    }
+
+    // TGE 之后，用户领取待领取额度中的全部 SCY
+    pub fn claim_allocation(ctx: Context<ClaimAllocation>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let tge_timestamp = ctx.accounts.state.tge_timestamp;
+        require!(tge_timestamp > 0 && now >= tge_timestamp, CustomError::TgeNotReached);
+        let state = &ctx.accounts.state;
+        require!(
+            state.soft_cap_usd == 0 || state.sale_status == SaleStatus::Succeeded,
+            CustomError::SaleNotSucceeded
+        );
+
+        let allocation = &mut ctx.accounts.allocation;
+        let claimable = allocation.amount.saturating_sub(allocation.claimed_amount);
+        require!(claimable > 0, CustomError::NothingToClaim);
+
+        allocation.claimed_amount += claimable;
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(claimable);
+
+        transfer_spl_from_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_spl_ata,
+            &ctx.accounts.user_spl_ata,
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            claimable
+        )?;
+
+        msg!("Claimed {} allocated SCY", claimable);
+        Ok(())
+    }
+
+    // 设置是否必须在销售轮次中购买：私募轮次进行期间开启，避免买家不传 round_index 绕过轮次的限制
+    pub fn set_round_required(ctx: Context<UpdateConfig>, round_required: bool) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        state.round_required = round_required;
+        Ok(())
+    }
+
+    // 创建或更新销售轮次；已有售出后不能再修改释放规则，额度也不能低于已售出数量
+    pub fn set_sale_round(ctx: Context<SetSaleRound>, index: u8, config: SaleRoundConfig) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            config.price_usd > 0 &&
+                config.start_time < config.end_time &&
+                is_valid_vesting_schedule(&config.vesting_schedule),
+            CustomError::InvalidSaleRoundConfig
+        );
 
-    #[account(mut, associated_token::mint = state.mint, associated_token::authority = admin)]
-    pub admin_ata: Account<'info, TokenAccount>, // 管理员的 SCY 代币账户
+        let sale_round = &mut ctx.accounts.sale_round;
+        require!(config.allocation >= sale_round.sold, CustomError::InvalidSaleRoundConfig);
+        require!(
+            sale_round.sold == 0 || sale_round.vesting_schedule == config.vesting_schedule,
+            CustomError::VestingScheduleMismatch
+        );
 
-    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
-    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
-    pub token_program: Program<'info, Token>,
-}
+        sale_round.index = index;
+        sale_round.price_usd = config.price_usd;
+        sale_round.start_time = config.start_time;
+        sale_round.end_time = config.end_time;
+        sale_round.allocation = config.allocation;
+        sale_round.per_wallet_cap = config.per_wallet_cap;
+        sale_round.allowlist_required = config.allowlist_required;
+        sale_round.vesting_schedule = config.vesting_schedule;
+        Ok(())
+    }
 
-#[derive(Accounts)] // 定义 Withdraw 所需的账户
-pub struct Withdraw<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>, // 管理员账户，必须对交易签名
-    #[account(mut, seeds = [b"state"], bump)]
-    pub state: Account<'info, State>, // 合约的全局状态账户
+    // 把钱包加入某个轮次的白名单
+    pub fn add_to_allowlist(ctx: Context<AddToAllowlist>, round_index: u8, wallet: Pubkey) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
 
-    #[account(mut, associated_token::mint = state.mint, associated_token::authority = admin)]
-    pub admin_ata: Account<'info, TokenAccount>, // 管理员的 SCY 代币账户
-    #[account(mut, associated_token::mint = state.usdc_mint, associated_token::authority = admin)]
-    pub admin_usdc_ata: Account<'info, TokenAccount>, // 管理员的 USDC 代币账户
+        let allowlist_entry = &mut ctx.accounts.allowlist_entry;
+        allowlist_entry.wallet = wallet;
+        allowlist_entry.round_index = round_index;
+        Ok(())
+    }
 
-    #[account(mut, associated_token::mint = state.usdt_mint, associated_token::authority = admin)]
-    pub admin_usdt_ata: Account<'info, TokenAccount>, // 管理员的 USDT 代币账户
+    // 把钱包移出白名单
+    pub fn remove_from_allowlist(ctx: Context<RemoveFromAllowlist>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        msg!(
+            "Removed {} from round {} allowlist",
+            ctx.accounts.allowlist_entry.wallet,
+            ctx.accounts.allowlist_entry.round_index
+        );
+        Ok(())
+    }
 
-    #[account(mut, seeds = [b"pda_spl_ata"], bump)]
-    pub pda_spl_ata: Account<'info, TokenAccount>, // 合约的 SCY 代币账户
+    // 钱包为自己注册推荐人账户，之后买家可以在购买时指定该推荐人
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referrer = &mut ctx.accounts.referrer;
+        referrer.owner = ctx.accounts.owner.key();
+        Ok(())
+    }
 
-    #[account(mut, seeds = [b"pda_usdc_ata"], bump)]
-    pub pda_usdc_ata: Account<'info, TokenAccount>, // 合约的 USDC 代币账户
+    // 推荐人领取累计的推荐奖励；软顶模式下需要募资成功
+    pub fn claim_referral_rewards(ctx: Context<ClaimReferralRewards>) -> Result<()> {
+        let state = &ctx.accounts.state;
+        require!(
+            state.soft_cap_usd == 0 || state.sale_status == SaleStatus::Succeeded,
+            CustomError::SaleNotSucceeded
+        );
 
-    #[account(mut, seeds = [b"pda_usdt_ata"], bump)]
-    pub pda_usdt_ata: Account<'info, TokenAccount>, // 合约的 USDT 代币账户
+        let referrer = &mut ctx.accounts.referrer;
+        let claimable = referrer.pending_rewards;
+        require!(claimable > 0, CustomError::NothingToClaim);
 
-    #[account(mut, seeds = [b"pda_sol"], bump)]
-    pub pda_sol_account: SystemAccount<'info>, // 合约的SOL账户
+        referrer.pending_rewards = 0;
+        referrer.claimed_rewards = referrer.claimed_rewards.checked_add(claimable).ok_or(CustomError::MathOverflow)?;
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(claimable);
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+        transfer_spl_from_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_spl_ata,
+            &ctx.accounts.user_spl_ata,
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            claimable
+        )?;
 
-#[derive(Accounts)] // 定义 UpdateAdmin 所需的账户
-pub struct UpdateAdmin<'info> {
-    #[account(mut, seeds = [b"state"], bump)]
-    pub state: Account<'info, State>, // 合约的全局状态账户
+        msg!("Claimed {} SCY referral rewards", claimable);
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub current_admin: Signer<'info>, // 当前管理员账户，必须签名交易
-    pub system_program: Program<'info, System>,
-}
+    // 软顶募资失败时推荐奖励永远不能领取，释放为其预留的 SCY，之后管理员可以通过 withdraw 取回
+    // 结果只取决于募资状态，任何人都可以调用；推荐统计数据保留
+    pub fn release_referral_rewards(ctx: Context<ReleaseReferralRewards>) -> Result<()> {
+        require!(ctx.accounts.state.sale_status == SaleStatus::Failed, CustomError::SaleNotFailed);
 
-#[derive(Accounts)]
-pub struct ClosePda<'info> {
-    #[account(mut, seeds = [b"state"], bump)]
-    pub state: Account<'info, State>, // 合约的全局状态账户
-    #[account(mut)]
-    pub pda_account: Account<'info, TokenAccount>, // USDC/SCY/USDT PDA
-    #[account(mut)]
-    pub admin: Signer<'info>, // 接收 SOL Rent 的 Admin Wallet
-    pub token_program: Program<'info, Token>,
-}
+        let referrer = &mut ctx.accounts.referrer;
+        let released = referrer.pending_rewards;
+        require!(released > 0, CustomError::NothingToClaim);
+        referrer.pending_rewards = 0;
 
-#[derive(Accounts)]
-pub struct CloseState<'info> {
-    #[account(mut, close = admin)]
-    pub state: Account<'info, State>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-}
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(released);
 
-// ----------------------------------------------------主体程序----------------------------------------------------
-#[program]
-pub mod scy_transfer {
-    use super::*;
+        msg!("Released {} SCY referral rewards of {}", released, referrer.owner);
+        Ok(())
+    }
 
-    // 初始化合约的 state 账户（只会被执行一次）
-    pub fn initialize_state(
-        ctx: Context<InitializeState>, // 调用该交易所需的所有账户信息() 使用 InitializeState 结构体中的账户)
-        usdc_mint: Pubkey, // USDC 代币的 Mint 地址
-        usdt_mint: Pubkey, // USDD 代币的 Mint 地址
-        mint: Pubkey // SCY 代币的 Mint 地址
+    // 登记优惠码：链上只保存 sha256(code)，明文由市场部门分发
+    pub fn register_promo_code(
+        ctx: Context<RegisterPromoCode>,
+        code_hash: [u8; 32],
+        bonus_bps: u16,
+        max_uses: u32,
+        expiry: i64
     ) -> Result<()> {
-        let state = &mut ctx.accounts.state; // state 账户是 一个 State 结构体，且可以修改
-        state.admin = *ctx.accounts.admin.key; // 将 admin 账户的 Pubkey 存入 state 账户，作为合约的 初始管理员
-        state.usdc_mint = usdc_mint;
-        state.usdt_mint = usdt_mint;
-        state.mint = mint;
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(
+            bonus_bps > 0 && (bonus_bps as u64) <= BPS_DENOMINATOR && max_uses > 0 &&
+                expiry > Clock::get()?.unix_timestamp,
+            CustomError::InvalidPromoCodeConfig
+        );
+
+        let promo_code = &mut ctx.accounts.promo_code;
+        promo_code.code_hash = code_hash;
+        promo_code.bonus_bps = bonus_bps;
+        promo_code.max_uses = max_uses;
+        promo_code.remaining_uses = max_uses;
+        promo_code.expiry = expiry;
         Ok(())
     }
 
-    // 初始化 pda_sol （用于储存、管理 SOL 的PDA账户） 结构体中会自动init
-    pub fn initialize_pda_sol(ctx: Context<InitializePdaSol>) -> Result<()> {
-        let rent = Rent::get()?; // 获取当前租金
-        let rent_exempt_lamports = rent.minimum_balance(0); // 计算 0 字节账户的租金豁免金额
-        let admin_signer = &ctx.accounts.admin;
-        let system_program = &ctx.accounts.system_program;
+    // 作废优惠码
+    pub fn remove_promo_code(ctx: Context<RemovePromoCode>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        Ok(())
+    }
 
-        // 构造 SOL 转账指令
-        let transfer_instruction = system_instruction::transfer(
-            ctx.accounts.admin.key,
-            &ctx.accounts.pda_sol_account.key,
-            rent_exempt_lamports
-        );
+    // 把钱包加入拒绝名单，之后该钱包无法再购买
+    pub fn add_to_denylist(ctx: Context<AddToDenylist>, wallet: Pubkey) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
 
-        anchor_lang::solana_program::program::invoke(
-            &transfer_instruction,
-            &[
-                admin_signer.to_account_info(),
-                ctx.accounts.pda_sol_account.to_account_info(),
-                system_program.to_account_info(),
-            ]
-        )?;
+        let denied = &mut ctx.accounts.denied;
+        denied.wallet = wallet;
+        denied.denied_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
 
-        msg!("PDA SOL account initialized: {}", ctx.accounts.pda_sol_account.key());
+    // 把钱包移出拒绝名单
+    pub fn remove_from_denylist(ctx: Context<RemoveFromDenylist>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        msg!("Removed {} from denylist", ctx.accounts.denied.wallet);
         Ok(())
     }
 
-    // 初始化 pda_spl_ata （用于储存、管理 SCY 的PDA账户） 结构体中会自动init
-    pub fn initialize_pda_spl_ata(ctx: Context<InitializePdaSplAta>) -> Result<()> {
-        msg!("PDA SPL ATA initialized: {}", ctx.accounts.pda_spl_ata.key());
+    // 用户按轮次的释放规则领取该轮次中已释放的 SCY
+    pub fn claim_round_vested(ctx: Context<ClaimRoundVested>, round_index: u8) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let round_purchase = &mut ctx.accounts.round_purchase;
+        let claimable = vested_amount(
+            round_purchase.purchased,
+            &ctx.accounts.sale_round.vesting_schedule,
+            ctx.accounts.state.tge_timestamp,
+            now
+        ).saturating_sub(round_purchase.claimed_amount);
+        require!(claimable > 0, CustomError::NothingToClaim);
+
+        round_purchase.claimed_amount += claimable;
+        let state = &mut ctx.accounts.state;
+        state.reserved_spl = state.reserved_spl.saturating_sub(claimable);
+
+        transfer_spl_from_pda(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pda_spl_ata,
+            &ctx.accounts.user_spl_ata,
+            &ctx.accounts.state,
+            ctx.bumps.state,
+            claimable
+        )?;
+
+        msg!("Claimed {} SCY from round {}", claimable, round_index);
         Ok(())
     }
 
-    // 初始化 pda_usdc_ata （用于储存、管理 USDC 的PDA账户） 结构体中会自动init
-    pub fn initialize_pda_usdc_ata(ctx: Context<InitializePdaUsdcAta>) -> Result<()> {
-        msg!("PDA USDC ATA initialized: {}", ctx.accounts.pda_usdc_ata.key());
+    // 更新价格操作员
+    pub fn set_price_operator(ctx: Context<UpdateConfig>, price_operator: Pubkey) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        require_keys_eq!(state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+
+        state.price_operator = price_operator;
         Ok(())
     }
 
-    // 初始化 pda_usdt_ata （用于储存、管理 USDT 的PDA账户） 结构体中会自动init
-    pub fn initialize_pda_usdt_ata(ctx: Context<InitializePdaUsdtAta>) -> Result<()> {
-        msg!("PDA USDT ATA initialized: {}", ctx.accounts.pda_usdt_ata.key());
+    // 预言机故障时设置紧急人工价格，duration 秒后过期，且只在管理员标记预言机故障（oracle_down）时生效
+    pub fn set_manual_price(
+        ctx: Context<SetManualPrice>,
+        feed_id: [u8; 32],
+        price: i64,
+        exponent: i32,
+        duration: i64
+    ) -> Result<()> {
+        let state = &ctx.accounts.state;
+        let operator = ctx.accounts.operator.key();
+        require!(
+            operator == state.admin || operator == state.price_operator,
+            CustomError::Unauthorized
+        );
+        require!(price > 0, CustomError::InvalidPrice);
+        require!(
+            duration > 0 && duration <= MAX_MANUAL_PRICE_DURATION,
+            CustomError::InvalidManualPriceDuration
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let manual_price = &mut ctx.accounts.manual_price;
+        manual_price.feed_id = feed_id;
+        manual_price.price = price;
+        manual_price.exponent = exponent;
+        manual_price.updated_at = now;
+        manual_price.expires_at = now + duration;
+        manual_price.set_by = operator;
+
+        msg!("Manual price set by {}, expires at {}", operator, manual_price.expires_at);
         Ok(())
     }
 
-    // 更新 admin 账户
-    pub fn update_admin(ctx: Context<UpdateAdmin>, new_admin: Pubkey) -> Result<()> {
-        let state = &mut ctx.accounts.state;
-        require_keys_eq!(state.admin, ctx.accounts.current_admin.key(), CustomError::Unauthorized); // 确保 current_admin 是现任管理员
+    // 管理员创建或更新某个 feed 的备用价格，购买时可作为额外的价格源传入
+    pub fn set_fallback_price(
+        ctx: Context<SetFallbackPrice>,
+        feed_id: [u8; 32],
+        price: i64,
+        exponent: i32
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.state.admin, ctx.accounts.admin.key(), CustomError::Unauthorized);
+        require!(price > 0, CustomError::InvalidPrice);
 
-        state.admin = new_admin; // 更新管理员地址
+        let fallback_price = &mut ctx.accounts.fallback_price;
+        fallback_price.feed_id = feed_id;
+        fallback_price.price = price;
+        fallback_price.exponent = exponent;
+        fallback_price.updated_at = Clock::get()?.unix_timestamp;
         Ok(())
     }
 
@@ -360,9 +3438,18 @@ pub mod scy_transfer {
         let seeds = &[b"state".as_ref(), &[ctx.bumps.state]];
         let signer = &[&seeds[..]];
 
-        // 可提取的SOL = PDA 账户中的 SOL
-        let withdrawable_sol = ctx.accounts.pda_sol_account.lamports();
-        if withdrawable_sol > 0 {
+        // 软顶模式下，募资成功前 SOL、USDC、USDT 留在托管中，不能提取
+        // 荷兰拍统一结算时，拍卖结束前最终成交价未定，资金不能提取；结束后保留尚未领取的差价
+        let auction_open = state.pricing_mode == PricingMode::DutchAuction &&
+            state.dutch_auction.uniform_clearing &&
+            Clock::get()?.unix_timestamp < state.dutch_auction.end_time;
+        let funds_unlocked =
+            (state.soft_cap_usd == 0 || state.sale_status == SaleStatus::Succeeded) && !auction_open;
+        let (sol_held, usdc_held, usdt_held) = auction_rebates_held(state);
+
+        // 可提取的SOL = PDA 账户中的 SOL（扣除需要保留的差价）
+        let withdrawable_sol = ctx.accounts.pda_sol_account.lamports().saturating_sub(sol_held);
+        if funds_unlocked && withdrawable_sol > 0 {
             let transfer_instruction = system_instruction::transfer(
                 &ctx.accounts.pda_sol_account.key(), // 从PDA账户
                 &ctx.accounts.admin.key(), // 转到Admin账户
@@ -380,8 +3467,8 @@ pub mod scy_transfer {
             )?;
         }
 
-        // 提取SCY
-        let pda_spl_balance = ctx.accounts.pda_spl_ata.amount;
+        // 提取SCY（已售出但尚未发放给用户的部分不能提取）
+        let pda_spl_balance = available_spl(&ctx.accounts.pda_spl_ata, state);
         if pda_spl_balance > 0 {
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
@@ -397,8 +3484,8 @@ pub mod scy_transfer {
         }
 
         // 提取 USDC
-        let usdc_balance = ctx.accounts.pda_usdc_ata.amount;
-        if usdc_balance > 0 {
+        let usdc_balance = ctx.accounts.pda_usdc_ata.amount.saturating_sub(usdc_held);
+        if funds_unlocked && usdc_balance > 0 {
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 SplTransfer {
@@ -412,8 +3499,8 @@ pub mod scy_transfer {
         }
 
         // 提取 USDT
-        let usdt_balance = ctx.accounts.pda_usdt_ata.amount;
-        if usdt_balance > 0 {
+        let usdt_balance = ctx.accounts.pda_usdt_ata.amount.saturating_sub(usdt_held);
+        if funds_unlocked && usdt_balance > 0 {
             let cpi_ctx = CpiContext::new_with_signer(
                 ctx.accounts.token_program.to_account_info(),
                 SplTransfer {
@@ -430,25 +3517,86 @@ pub mod scy_transfer {
     }
 
     // 用户将 SOL转给 项目方（admin） 的SOL 钱包，PDA pda_scy_ata将 SCY 转给 用户 user_scy_ata
-    pub fn buy_spl_with_sol(ctx: Context<BuySplWithSol>, lamports_to_pay: u64) -> Result<()> {
+    // options 各字段的含义见 PurchaseOptions；allow_partial 为 true 时，库存不足则只买下剩余库存，并只收取对应的 SOL
+    // recipient 账户为接收 SCY 的钱包，为自己购买时不传；同一 client_order_id 重复提交会失败
+    pub fn buy_spl_with_sol(ctx: Context<BuySplWithSol>, lamports_to_pay: u64, options: PurchaseOptions) -> Result<()> {
+        let PurchaseOptions {
+            client_ref,
+            allow_partial,
+            round_index,
+            allowlist_proof,
+            kyc_attestation,
+            promo_code,
+            client_order_id,
+        } = options;
+        let recipient = ctx.accounts.recipient.as_ref().map_or(ctx.accounts.user.key(), Key::key);
+        require_sale_open(&ctx.accounts.state)?;
+        require_not_denied(&ctx.accounts.denied)?;
+        require_not_denied(&ctx.accounts.recipient_denied)?;
+        // 软顶退款按同一钱包处理付款记录和待领取额度，软顶模式下不支持代为购买
+        require!(
+            ctx.accounts.state.soft_cap_usd == 0 || recipient == ctx.accounts.user.key(),
+            CustomError::RecipientUnavailableWithSoftCap
+        );
+
         // 1. 使用预言机获得 SOL/USD，计算应向用户发放的 SCY 数量
         let spl_precision = (10_u64).pow(ctx.accounts.mint.decimals as u32); // 动态计算 SCY 代币的精度
 
-        let spl_price_in_usd = 0.02f64; // 1 SCY = 0.02 USD
-        let lamports_per_sol = 1_000_000_000u64; // 1 SOL = 10^9 lamports
-
-        let price_update = &mut ctx.accounts.price_update; // 使用预言机获取价格
-        let maximum_age: u64 = 60; // 60s内更新的价格
+        let price_update = &ctx.accounts.price_update; // 使用预言机获取价格
         let feed_id: [u8; 32] = get_feed_id_from_hex(
             "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"
         )?;
-        let price = price_update.get_price_no_older_than(&Clock::get()?, maximum_age, &feed_id)?;
-        let sol_price_in_usd: f64 = (price.price as f64) * (10f64).powi(price.exponent); // 获取 Pyth 预言机的 SOL/USD 价格
+        // price_update 之外的价格源通过 remaining_accounts 传入，取所有未过期价格源的中位数
+        let (price, manual_price) = get_purchase_price(
+            price_update,
+            ctx.remaining_accounts,
+            ctx.accounts.manual_price.as_deref(),
+            &feed_id,
+            &ctx.accounts.state
+        )?;
+
+        // 指定轮次时使用该轮次的价格，并校验轮次时间和白名单
+        let round = active_round(
+            &ctx.accounts.state,
+            round_index,
+            ctx.accounts.sale_round.as_deref(),
+            ctx.accounts.allowlist_entry.is_some()
+        )?;
+        // 开启 Merkle 白名单时校验 proof，并按 max_allocation 限制该钱包的累计购买数量
+        let allowlist_left = allowlist_remaining(
+            &ctx.accounts.state,
+            recipient,
+            &ctx.accounts.buyer_info,
+            allowlist_proof.as_ref()
+        )?;
+        // 开启 KYC 时校验 attester 签名，并按签名等级限制该钱包的累计购买数量
+        let kyc_left = kyc_remaining(
+            &ctx.accounts.state,
+            &ctx.accounts.instructions_sysvar,
+            recipient,
+            &ctx.accounts.buyer_info,
+            kyc_attestation.as_ref()
+        )?;
+        // 轮次开启持币门槛时校验用户的合作方代币余额，按比例分配额度时限制该钱包在该轮次的累计购买数量
+        let gating_left = gating_remaining(
+            round.as_ref(),
+            recipient,
+            ctx.accounts.round_purchase.as_deref(),
+            ctx.accounts.gating_token_account.as_deref()
+        )?;
+        // 轮次价格、价格档位和默认价格都由 price_spl_amount 计算，与 quote 使用同一套逻辑
+        let spl_sold = ctx.accounts.sale_stats.spl_sold;
+        // 大额购买奖励和优惠码奖励都计入 SCY 数量，同样受 MAX_PURCHASE、轮次额度、钱包上限和库存的限制
+        let promo_bps = promo_bonus_bps(ctx.accounts.promo_code.as_deref(), promo_code.as_deref())?;
+        let base_spl_for = |usd_value: u64| {
+            let spl_amount = price_spl_amount(&ctx.accounts.state, round.as_ref(), spl_sold, usd_value, spl_precision)?;
+            apply_volume_bonus(&ctx.accounts.state, usd_value, spl_amount)
+        };
+        let spl_for = |usd_value: u64| apply_promo_bonus(promo_bps, base_spl_for(usd_value)?);
 
-        let sol_amount = (lamports_to_pay as f64) / (lamports_per_sol as f64); // the amount of sol
-        let user_pay_in_usd = sol_amount * sol_price_in_usd; // the value in USD
-        let spl_amount_float = (user_pay_in_usd / spl_price_in_usd) * (spl_precision as f64); // SCY 最小单位数量
-        let spl_amount: u64 = spl_amount_float.floor() as u64; // 转成整型
+        let usd_value = to_usd_value(lamports_to_pay, LAMPORTS_PER_SOL_DECIMALS, &price)?; // 用户支付的 SOL 折合的 USD（micro-USD）
+        let spl_amount = spl_for(usd_value)?; // SCY 最小单位数量
+        let (requested_payment_amount, requested_spl_amount) = (lamports_to_pay, spl_amount);
 
         // 2.验证用户购买的SCY数量是否符合要求
         if spl_amount < MIN_PURCHASE * spl_precision {
@@ -459,9 +3607,29 @@ pub mod scy_transfer {
             return Err(CustomError::PurchaseAmountTooHigh.into());
         }
 
-        if ctx.accounts.pda_spl_ata.amount < spl_amount {
-            return Err(CustomError::InsufficientSPLBalance.into());
-        }
+        // 超过硬顶时只按剩余额度成交，并重新计算 USD 金额和 SCY 数量
+        let lamports_to_pay = cap_payment(&ctx.accounts.state, &ctx.accounts.sale_stats, lamports_to_pay, usd_value)?;
+        let usd_value = to_usd_value(lamports_to_pay, LAMPORTS_PER_SOL_DECIMALS, &price)?;
+        let spl_amount = spl_for(usd_value)?;
+
+        // 库存（以及轮次额度、钱包上限）不足时按可购买数量部分成交（需 allow_partial），发放数量不超过可购买数量
+        let available = available_spl(&ctx.accounts.pda_spl_ata, &ctx.accounts.state);
+        let (limit, limit_error) = purchase_limit(
+            available,
+            round.as_ref(),
+            ctx.accounts.round_purchase.as_deref(),
+            &[
+                (allowlist_left, CustomError::WalletAllocationExceeded),
+                (kyc_left, CustomError::KycLimitExceeded),
+                (gating_left, CustomError::GatingAllocationExceeded),
+            ]
+        )?;
+        let lamports_to_pay = fill_from_inventory(limit, lamports_to_pay, spl_amount, allow_partial, limit_error, |limit| {
+            from_usd_value(usd_for_spl_amount(limit, spl_for)?, LAMPORTS_PER_SOL_DECIMALS, &price)
+        })?;
+        let usd_value = to_usd_value(lamports_to_pay, LAMPORTS_PER_SOL_DECIMALS, &price)?;
+        let spl_amount = spl_for(usd_value)?.min(limit);
+        let promo_bonus = spl_amount.saturating_sub(base_spl_for(usd_value)?);
 
         // 3. 接收用户的 SOL ，将SOL 传入 PDA账户
         let user_signer = &ctx.accounts.user; // 用户的发送sol普通钱包
@@ -469,7 +3637,7 @@ pub mod scy_transfer {
 
         let transfer_instruction = system_instruction::transfer(
             user_signer.key,
-            &ctx.accounts.pda_sol_account.key, //修改为 传入 PDA账户
+            ctx.accounts.pda_sol_account.key, //修改为 传入 PDA账户
             lamports_to_pay
         );
 
@@ -481,32 +3649,139 @@ pub mod scy_transfer {
                 system_program.to_account_info(),
             ]
         )?;
+        record_escrow(
+            ctx.accounts.escrow.as_deref_mut(),
+            &ctx.accounts.state,
+            ctx.accounts.user.key(),
+            PaymentAsset::Sol,
+            lamports_to_pay
+        )?;
+        if round.is_none() {
+            record_auction_purchase(
+                &mut ctx.accounts.state,
+                ctx.accounts.auction_bid.as_deref_mut(),
+                ctx.accounts.user.key(),
+                PaymentAsset::Sol,
+                lamports_to_pay
+            )?;
+        }
 
-        // 4.PDA 账户 pda_spl_ata 向用户 user_spl_ata 发送 SCY
-        let seeds = &[b"state".as_ref(), &[ctx.bumps.state]];
-        let signer = &[&seeds[..]];
+        // 使用优惠码时扣减一次剩余次数，奖励已经计入 spl_amount
+        if let Some(promo) = ctx.accounts.promo_code.as_deref_mut() {
+            promo.remaining_uses -= 1;
+        }
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            SplTransfer {
-                from: ctx.accounts.pda_spl_ata.to_account_info(),
-                to: ctx.accounts.user_spl_ata.to_account_info(),
-                authority: ctx.accounts.state.to_account_info(),
-            },
-            signer
-        );
-        token::transfer(cpi_ctx, spl_amount)?;
+        // 4.按发放模式发放 SCY：PDA 账户 pda_spl_ata 直接向用户 user_spl_ata 发送，或记入用户的锁仓记录 / 待领取额度
+        // 轮次购买记入用户在该轮次的购买记录，按轮次的释放规则领取
+        if round.is_some() {
+            credit_round(
+                ctx.accounts.sale_round.as_deref_mut().ok_or(CustomError::SaleRoundRequired)?,
+                ctx.accounts.round_purchase.as_deref_mut().ok_or(CustomError::RoundPurchaseRequired)?,
+                &mut ctx.accounts.state,
+                recipient,
+                spl_amount
+            )?;
+        } else {
+            deliver_spl(
+                &mut ctx.accounts.state,
+                ctx.bumps.state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.pda_spl_ata,
+                &ctx.accounts.user_spl_ata,
+                ctx.accounts.vesting_position.as_deref_mut(),
+                ctx.accounts.allocation.as_deref_mut(),
+                recipient,
+                spl_amount
+            )?;
+        }
+
+        // 5. 写入成交回执（可选），并更新销售统计
+        if let Some(receipt) = ctx.accounts.receipt.as_deref_mut() {
+            write_receipt(
+                receipt,
+                &ctx.accounts.buyer_info,
+                recipient,
+                ctx.accounts.user.key(),
+                token::spl_token::native_mint::ID,
+                lamports_to_pay,
+                spl_amount,
+                &price,
+                client_ref
+            )?;
+        }
+
+        record_order(
+            ctx.accounts.order.as_deref_mut(),
+            client_order_id,
+            ctx.accounts.user.key(),
+            token::spl_token::native_mint::ID,
+            lamports_to_pay,
+            spl_amount
+        )?;
+
+        let sale_stats = &mut ctx.accounts.sale_stats;
+        sale_stats.sol_raised = sale_stats.sol_raised
+            .checked_add(lamports_to_pay)
+            .ok_or(CustomError::MathOverflow)?;
+        record_purchase(sale_stats, &mut ctx.accounts.buyer_info, recipient, spl_amount, usd_value)?;
+
+        // 指定推荐人时，按比例从 pda_spl_ata 的剩余库存中额外拿出 SCY 记入推荐人的待领取奖励
+        if let Some(referrer) = ctx.accounts.referrer.as_deref_mut() {
+            accrue_referral(
+                &mut ctx.accounts.state,
+                referrer,
+                ctx.accounts.user.key(),
+                recipient,
+                available.saturating_sub(spl_amount),
+                spl_amount,
+                usd_value
+            )?;
+        }
+
+        emit!(SplPurchased {
+            buyer: ctx.accounts.user.key(),
+            recipient,
+            payment_mint: token::spl_token::native_mint::ID,
+            requested_payment_amount,
+            requested_spl_amount,
+            payment_amount: lamports_to_pay,
+            spl_amount,
+            bonus_spl_amount: promo_bonus,
+            client_order_id,
+            price: price.price,
+            exponent: price.exponent,
+            manual_price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 
     // 用户使用 USDC/USDT 购买 SCY 代币， USDC/USDT 会转入 PDA 账户， pda_spl_ata 向用户 user_spl_ata 转移 SCY 代币
-    pub fn buy_spl_with_spl(ctx: Context<BuySplWithSpl>, token_amount: u64) -> Result<()> {
+    // options 各字段的含义见 PurchaseOptions；allow_partial 为 true 时，库存不足则只买下剩余库存，并只收取对应的 USDC/USDT
+    // recipient 账户为接收 SCY 的钱包，为自己购买时不传；同一 client_order_id 重复提交会失败
+    pub fn buy_spl_with_spl(ctx: Context<BuySplWithSpl>, token_amount: u64, options: PurchaseOptions) -> Result<()> {
+        let PurchaseOptions {
+            client_ref,
+            allow_partial,
+            round_index,
+            allowlist_proof,
+            kyc_attestation,
+            promo_code,
+            client_order_id,
+        } = options;
+        let recipient = ctx.accounts.recipient.as_ref().map_or(ctx.accounts.user.key(), Key::key);
+        require_sale_open(&ctx.accounts.state)?;
+        require_not_denied(&ctx.accounts.denied)?;
+        require_not_denied(&ctx.accounts.recipient_denied)?;
+        // 软顶退款按同一钱包处理付款记录和待领取额度，软顶模式下不支持代为购买
+        require!(
+            ctx.accounts.state.soft_cap_usd == 0 || recipient == ctx.accounts.user.key(),
+            CustomError::RecipientUnavailableWithSoftCap
+        );
+
         // 1. 计算用户应得的 SCY
         let spl_precision = (10_u64).pow(ctx.accounts.mint.decimals as u32); // 动态计算 SCY 代币的精度
 
-        let spl_price_in_usd = 0.02_f64;
-        let decimals = 1_000_000u64; // USDT/USDC 的精度为 6
-        let maximum_age: u64 = 60; // 60s内更新的价格
         const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
         const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
 
@@ -518,7 +3793,7 @@ pub mod scy_transfer {
             _ => None,
         };
 
-        let price_update = &mut ctx.accounts.price_update;
+        let price_update = &ctx.accounts.price_update;
 
         let feed_id: [u8; 32] = match feed_ids {
             Some(id) => get_feed_id_from_hex(id)?,
@@ -527,13 +3802,57 @@ pub mod scy_transfer {
             }
         };
 
-        let price = price_update.get_price_no_older_than(&Clock::get()?, maximum_age, &feed_id)?;
-        let usdc_price_in_usd: f64 = (price.price as f64) * (10f64).powi(price.exponent);
+        // 稳定币按 1:1 计价，这里只校验预言机价格的时效、偏离和价格源数量，价格异常时拒绝购买
+        let (price, manual_price) = get_purchase_price(
+            price_update,
+            ctx.remaining_accounts,
+            ctx.accounts.manual_price.as_deref(),
+            &feed_id,
+            &ctx.accounts.state
+        )?;
 
-        let spl_amount_float =
-            ((token_amount as f64) / (decimals as f64) / spl_price_in_usd) * (spl_precision as f64);
+        // 指定轮次时使用该轮次的价格，并校验轮次时间和白名单
+        let round = active_round(
+            &ctx.accounts.state,
+            round_index,
+            ctx.accounts.sale_round.as_deref(),
+            ctx.accounts.allowlist_entry.is_some()
+        )?;
+        // 开启 Merkle 白名单时校验 proof，并按 max_allocation 限制该钱包的累计购买数量
+        let allowlist_left = allowlist_remaining(
+            &ctx.accounts.state,
+            recipient,
+            &ctx.accounts.buyer_info,
+            allowlist_proof.as_ref()
+        )?;
+        // 开启 KYC 时校验 attester 签名，并按签名等级限制该钱包的累计购买数量
+        let kyc_left = kyc_remaining(
+            &ctx.accounts.state,
+            &ctx.accounts.instructions_sysvar,
+            recipient,
+            &ctx.accounts.buyer_info,
+            kyc_attestation.as_ref()
+        )?;
+        // 轮次开启持币门槛时校验用户的合作方代币余额，按比例分配额度时限制该钱包在该轮次的累计购买数量
+        let gating_left = gating_remaining(
+            round.as_ref(),
+            recipient,
+            ctx.accounts.round_purchase.as_deref(),
+            ctx.accounts.gating_token_account.as_deref()
+        )?;
+        // 轮次价格、价格档位和默认价格都由 price_spl_amount 计算，与 quote 使用同一套逻辑
+        let spl_sold = ctx.accounts.sale_stats.spl_sold;
+        // 大额购买奖励和优惠码奖励都计入 SCY 数量，同样受 MAX_PURCHASE、轮次额度、钱包上限和库存的限制
+        let promo_bps = promo_bonus_bps(ctx.accounts.promo_code.as_deref(), promo_code.as_deref())?;
+        let base_spl_for = |usd_value: u64| {
+            let spl_amount = price_spl_amount(&ctx.accounts.state, round.as_ref(), spl_sold, usd_value, spl_precision)?;
+            apply_volume_bonus(&ctx.accounts.state, usd_value, spl_amount)
+        };
+        let spl_for = |usd_value: u64| apply_promo_bonus(promo_bps, base_spl_for(usd_value)?);
 
-        let spl_amount: u64 = spl_amount_float.floor() as u64; // 计算最终的 SCY 数量并转成整型
+        let usd_value = token_amount; // USDT/USDC 的精度为 6，与 micro-USD 相同，按 1:1 计价
+        let spl_amount = spl_for(usd_value)?; // 计算最终的 SCY 数量
+        let (requested_payment_amount, requested_spl_amount) = (token_amount, spl_amount);
 
         // 2.验证用户购买的SCY数量是否符合要求
         if spl_amount < MIN_PURCHASE * spl_precision {
@@ -544,14 +3863,33 @@ pub mod scy_transfer {
             return Err(CustomError::PurchaseAmountTooHigh.into());
         }
 
-        if ctx.accounts.pda_spl_ata.amount < spl_amount {
-            return Err(CustomError::InsufficientSPLBalance.into());
-        }
+        // 超过硬顶时只按剩余额度成交，并重新计算 SCY 数量
+        let token_amount = cap_payment(&ctx.accounts.state, &ctx.accounts.sale_stats, token_amount, usd_value)?;
+        let spl_amount = spl_for(token_amount)?;
+
+        // 库存（以及轮次额度、钱包上限）不足时按可购买数量部分成交（需 allow_partial），发放数量不超过可购买数量
+        let available = available_spl(&ctx.accounts.pda_spl_ata, &ctx.accounts.state);
+        let (limit, limit_error) = purchase_limit(
+            available,
+            round.as_ref(),
+            ctx.accounts.round_purchase.as_deref(),
+            &[
+                (allowlist_left, CustomError::WalletAllocationExceeded),
+                (kyc_left, CustomError::KycLimitExceeded),
+                (gating_left, CustomError::GatingAllocationExceeded),
+            ]
+        )?;
+        let token_amount = fill_from_inventory(limit, token_amount, spl_amount, allow_partial, limit_error, |limit| {
+            usd_for_spl_amount(limit, spl_for)
+        })?;
+        let usd_value = token_amount;
+        let spl_amount = spl_for(usd_value)?.min(limit);
+        let promo_bonus = spl_amount.saturating_sub(base_spl_for(usd_value)?);
 
         // 选择 pda_usdc_ata或pda_usdt_ata 账户接收 USDC/USDT
-        let to_account_info = match user_mint_key.as_str() {
-            USDC_MINT => ctx.accounts.pda_usdc_ata.to_account_info(),
-            USDT_MINT => ctx.accounts.pda_usdt_ata.to_account_info(),
+        let (to_account_info, asset) = match user_mint_key.as_str() {
+            USDC_MINT => (ctx.accounts.pda_usdc_ata.to_account_info(), PaymentAsset::Usdc),
+            USDT_MINT => (ctx.accounts.pda_usdt_ata.to_account_info(), PaymentAsset::Usdt),
             _ => {
                 return Err(CustomError::InvalidMint.into());
             }
@@ -564,21 +3902,289 @@ pub mod scy_transfer {
             authority: ctx.accounts.user.to_account_info(),
         });
         token::transfer(cpi_ctx, token_amount)?;
+        record_escrow(ctx.accounts.escrow.as_deref_mut(), &ctx.accounts.state, ctx.accounts.user.key(), asset, token_amount)?;
+        if round.is_none() {
+            record_auction_purchase(
+                &mut ctx.accounts.state,
+                ctx.accounts.auction_bid.as_deref_mut(),
+                ctx.accounts.user.key(),
+                asset,
+                token_amount
+            )?;
+        }
 
-        // 把 SCY 从PDA账户pda_spl_ata 转给用户user_spl_ata
-        let seeds = &[b"state".as_ref(), &[ctx.bumps.state]];
-        let signer = &[&seeds[..]];
+        // 使用优惠码时扣减一次剩余次数，奖励已经计入 spl_amount
+        if let Some(promo) = ctx.accounts.promo_code.as_deref_mut() {
+            promo.remaining_uses -= 1;
+        }
+
+        // 把 SCY 从PDA账户pda_spl_ata 转给用户user_spl_ata，锁仓 / TGE 后领取模式下记入用户的记录
+        // 轮次购买记入用户在该轮次的购买记录，按轮次的释放规则领取
+        if round.is_some() {
+            credit_round(
+                ctx.accounts.sale_round.as_deref_mut().ok_or(CustomError::SaleRoundRequired)?,
+                ctx.accounts.round_purchase.as_deref_mut().ok_or(CustomError::RoundPurchaseRequired)?,
+                &mut ctx.accounts.state,
+                recipient,
+                spl_amount
+            )?;
+        } else {
+            deliver_spl(
+                &mut ctx.accounts.state,
+                ctx.bumps.state,
+                &ctx.accounts.token_program,
+                &ctx.accounts.pda_spl_ata,
+                &ctx.accounts.user_spl_ata,
+                ctx.accounts.vesting_position.as_deref_mut(),
+                ctx.accounts.allocation.as_deref_mut(),
+                recipient,
+                spl_amount
+            )?;
+        }
+
+        // 写入成交回执（可选），并更新销售统计
+        if let Some(receipt) = ctx.accounts.receipt.as_deref_mut() {
+            write_receipt(
+                receipt,
+                &ctx.accounts.buyer_info,
+                recipient,
+                ctx.accounts.user.key(),
+                ctx.accounts.user_mint.key(),
+                token_amount,
+                spl_amount,
+                &price,
+                client_ref
+            )?;
+        }
+
+        record_order(
+            ctx.accounts.order.as_deref_mut(),
+            client_order_id,
+            ctx.accounts.user.key(),
+            ctx.accounts.user_mint.key(),
+            token_amount,
+            spl_amount
+        )?;
+
+        let sale_stats = &mut ctx.accounts.sale_stats;
+        let raised = if asset == PaymentAsset::Usdc {
+            &mut sale_stats.usdc_raised
+        } else {
+            &mut sale_stats.usdt_raised
+        };
+        *raised = raised.checked_add(token_amount).ok_or(CustomError::MathOverflow)?;
+        record_purchase(sale_stats, &mut ctx.accounts.buyer_info, recipient, spl_amount, usd_value)?;
+
+        // 指定推荐人时，按比例从 pda_spl_ata 的剩余库存中额外拿出 SCY 记入推荐人的待领取奖励
+        if let Some(referrer) = ctx.accounts.referrer.as_deref_mut() {
+            accrue_referral(
+                &mut ctx.accounts.state,
+                referrer,
+                ctx.accounts.user.key(),
+                recipient,
+                available.saturating_sub(spl_amount),
+                spl_amount,
+                usd_value
+            )?;
+        }
+
+        emit!(SplPurchased {
+            buyer: ctx.accounts.user.key(),
+            recipient,
+            payment_mint: ctx.accounts.user_mint.key(),
+            requested_payment_amount,
+            requested_spl_amount,
+            payment_amount: token_amount,
+            spl_amount,
+            bonus_spl_amount: promo_bonus,
+            client_order_id,
+            price: price.price,
+            exponent: price.exponent,
+            manual_price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // 批量购买：付款方一次支付全部接收人的 SCY，按 SCY 总数一次计价，再逐个转入接收人的 ATA（不存在时由付款方付费创建）
+    // spl_amounts[i] 对应 remaining_accounts 中第 i 组 (recipient, recipient 的 SCY ATA, recipient 的拒绝名单 PDA, recipient 的 BuyerInfo PDA)，
+    // 这些账户之前是 price_source_count 个额外的价格源（与单笔购买的 remaining_accounts 相同）
+    // 任意一个接收人无效（数量超出单笔限制、ATA 不匹配、在拒绝名单中、重复出现）时整笔交易失败
+    // 接收人的 BuyerInfo 不存在时由付款方付费创建，购买记录与单笔代为购买一样记在接收人名下
+    // client_order_id 与单笔购买相同，传入时创建 order 账户防止重复提交
+    pub fn buy_batch_with_spl<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyBatchWithSpl<'info>>,
+        spl_amounts: Vec<u64>,
+        price_source_count: u8,
+        client_order_id: Option<[u8; 32]>
+    ) -> Result<()> {
+        require_sale_open(&ctx.accounts.state)?;
+        require_not_denied(&ctx.accounts.denied)?;
+        require_batch_available(&ctx.accounts.state)?;
+        // 批量购买不支持轮次，开启 round_required 时与单笔购买一样不能按默认价格购买
+        require!(!ctx.accounts.state.round_required, CustomError::RoundIndexRequired);
+        require_auction_open(&ctx.accounts.state)?;
+        let price_source_count = price_source_count as usize;
+        require!(
+            !spl_amounts.is_empty() &&
+                spl_amounts.len() <= MAX_BATCH_RECIPIENTS &&
+                ctx.remaining_accounts.len() == price_source_count + spl_amounts.len() * BATCH_ACCOUNTS_PER_RECIPIENT,
+            CustomError::InvalidBatch
+        );
+        let (price_sources, recipient_accounts) = ctx.remaining_accounts.split_at(price_source_count);
+
+        let spl_precision = (10_u64).pow(ctx.accounts.mint.decimals as u32);
+        const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+        let (feed_id, to_account_info, asset) = match ctx.accounts.user_mint.key().to_string().as_str() {
+            USDC_MINT => (
+                "0xeaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a",
+                ctx.accounts.pda_usdc_ata.to_account_info(),
+                PaymentAsset::Usdc,
+            ),
+            USDT_MINT => (
+                "0x2b89b9dc8fdf9f34709a5b106b472f0f39bb6ca9ce04b0fd7f2e971688e2e53b",
+                ctx.accounts.pda_usdt_ata.to_account_info(),
+                PaymentAsset::Usdt,
+            ),
+            _ => {
+                return Err(CustomError::InvalidMint.into());
+            }
+        };
+        // 与单笔购买一样校验稳定币价格的时效、偏离和价格源数量
+        let (price, manual_price) = get_purchase_price(
+            &ctx.accounts.price_update,
+            price_sources,
+            ctx.accounts.manual_price.as_deref(),
+            &get_feed_id_from_hex(feed_id)?,
+            &ctx.accounts.state
+        )?;
 
-        let cpi_ctx_spl_transfer = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            SplTransfer {
-                from: ctx.accounts.pda_spl_ata.to_account_info(),
-                to: ctx.accounts.user_spl_ata.to_account_info(),
-                authority: ctx.accounts.state.to_account_info(),
-            },
-            signer
+        // 1. 逐个校验接收人的购买数量，再按 SCY 总数一次计价
+        for &spl_amount in &spl_amounts {
+            require!(spl_amount >= MIN_PURCHASE * spl_precision, CustomError::PurchaseAmountTooLow);
+            require!(spl_amount <= MAX_PURCHASE * spl_precision, CustomError::PurchaseAmountTooHigh);
+        }
+        let total_spl = spl_amounts
+            .iter()
+            .try_fold(0u64, |total, amount| total.checked_add(*amount))
+            .ok_or(CustomError::MathOverflow)?;
+        require!(
+            total_spl <= available_spl(&ctx.accounts.pda_spl_ata, &ctx.accounts.state),
+            CustomError::InsufficientSPLBalance
+        );
+
+        let spl_sold = ctx.accounts.sale_stats.spl_sold;
+        let token_amount = usd_for_spl_amount(total_spl, |usd_value| {
+            let spl_amount = price_spl_amount(&ctx.accounts.state, None, spl_sold, usd_value, spl_precision)?;
+            apply_volume_bonus(&ctx.accounts.state, usd_value, spl_amount)
+        })?; // USDT/USDC 的精度为 6，与 micro-USD 相同，按 1:1 计价
+        let usd_value = token_amount;
+        // 批量购买不做部分成交，超过硬顶时直接拒绝
+        require!(
+            cap_payment(&ctx.accounts.state, &ctx.accounts.sale_stats, token_amount, usd_value)? == token_amount,
+            CustomError::HardCapExceeded
         );
-        token::transfer(cpi_ctx_spl_transfer, spl_amount)?;
+
+        // 2. 收取付款方的 USDC/USDT
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), SplTransfer {
+            from: ctx.accounts.user_token_ata.to_account_info(),
+            to: to_account_info,
+            authority: ctx.accounts.user.to_account_info(),
+        });
+        token::transfer(cpi_ctx, token_amount)?;
+
+        // 3. 逐个转给接收人的 ATA，并把购买记录记在接收人名下；USD 金额按 SCY 数量分摊，余数计入最后一个接收人
+        let mut recipients: Vec<Pubkey> = Vec::with_capacity(spl_amounts.len());
+        let mut usd_left = usd_value;
+        for (i, (accounts, &spl_amount)) in recipient_accounts.chunks(BATCH_ACCOUNTS_PER_RECIPIENT).zip(&spl_amounts).enumerate() {
+            let (recipient, recipient_ata, recipient_denied, buyer_info) = (&accounts[0], &accounts[1], &accounts[2], &accounts[3]);
+            require!(!recipients.contains(recipient.key), CustomError::DuplicateBatchRecipient);
+            let (denied_address, _) = Pubkey::find_program_address(&[b"denied", recipient.key.as_ref()], &crate::ID);
+            require_keys_eq!(denied_address, recipient_denied.key(), CustomError::InvalidBatchRecipient);
+            require_not_denied(recipient_denied)?;
+            require_keys_eq!(
+                associated_token::get_associated_token_address(recipient.key, &ctx.accounts.mint.key()),
+                recipient_ata.key(),
+                CustomError::InvalidBatchRecipient
+            );
+
+            if recipient_ata.data_is_empty() {
+                associated_token::create_idempotent(
+                    CpiContext::new(ctx.accounts.associated_token_program.to_account_info(), associated_token::Create {
+                        payer: ctx.accounts.user.to_account_info(),
+                        associated_token: recipient_ata.clone(),
+                        authority: recipient.clone(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                    })
+                )?;
+            }
+            let recipient_ata = Account::<TokenAccount>::try_from(recipient_ata)?;
+            transfer_spl_from_pda(
+                &ctx.accounts.token_program,
+                &ctx.accounts.pda_spl_ata,
+                &recipient_ata,
+                &ctx.accounts.state,
+                ctx.bumps.state,
+                spl_amount
+            )?;
+
+            let usd_share = if i + 1 == spl_amounts.len() {
+                usd_left
+            } else {
+                (((usd_value as u128) * (spl_amount as u128)) / (total_spl as u128)) as u64
+            };
+            usd_left -= usd_share;
+            let mut buyer_info = load_buyer_info(buyer_info, recipient.key(), &ctx.accounts.user, &ctx.accounts.system_program)?;
+            record_purchase(&mut ctx.accounts.sale_stats, &mut buyer_info, recipient.key(), spl_amount, usd_share)?;
+            buyer_info.exit(&crate::ID)?;
+            recipients.push(recipient.key());
+        }
+
+        // 4. 写入订单记录，更新各支付资产的募资统计
+        record_order(
+            ctx.accounts.order.as_deref_mut(),
+            client_order_id,
+            ctx.accounts.user.key(),
+            ctx.accounts.user_mint.key(),
+            token_amount,
+            total_spl
+        )?;
+        let sale_stats = &mut ctx.accounts.sale_stats;
+        let raised = if asset == PaymentAsset::Usdc {
+            &mut sale_stats.usdc_raised
+        } else {
+            &mut sale_stats.usdt_raised
+        };
+        *raised = raised.checked_add(token_amount).ok_or(CustomError::MathOverflow)?;
+
+        emit!(BatchPurchased {
+            buyer: ctx.accounts.user.key(),
+            payment_mint: ctx.accounts.user_mint.key(),
+            payment_amount: token_amount,
+            spl_amount: total_spl,
+            recipients,
+            spl_amounts,
+            client_order_id,
+            price: price.price,
+            exponent: price.exponent,
+            manual_price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // 付款方在回执导出后关闭回执账户，取回租金；代他人购买时由付款方而不是接收方关闭
+    pub fn close_receipt(ctx: Context<CloseReceipt>) -> Result<()> {
+        msg!("Receipt {} closed by {}", ctx.accounts.receipt.index, ctx.accounts.payer.key());
+        Ok(())
+    }
+
+    // 买家在后端完成对账后关闭订单记录，取回租金；关闭后同一个 client_order_id 可以再次使用，不再防止重复提交
+    pub fn close_order(ctx: Context<CloseOrder>) -> Result<()> {
+        msg!("Order closed by {}", ctx.accounts.buyer.key());
         Ok(())
     }
 
@@ -607,7 +4213,7 @@ pub mod scy_transfer {
     }
 
     // 关闭state账户
-    pub fn close_state(ctx: Context<CloseState>) -> Result<()> {
+    pub fn close_state(_ctx: Context<CloseState>) -> Result<()> {
         msg!("State account successfully closed. SOL Rent returned to Admin.");
         Ok(())
     }
@@ -626,4 +4232,168 @@ pub enum CustomError {
     InvalidMint,
     #[msg("Unauthorized Access")]
     Unauthorized,
+    #[msg("Oracle price must be positive.")]
+    InvalidPrice,
+    #[msg("Spot price deviates too far from the EMA price.")]
+    PriceDeviationTooHigh,
+    #[msg("Price deviation threshold must be between 1 and 10000 bps.")]
+    InvalidPriceGuard,
+    #[msg("Not enough fresh and agreeing price sources.")]
+    NotEnoughPriceSources,
+    #[msg("Too many price sources.")]
+    TooManyPriceSources,
+    #[msg("The same price source was passed more than once.")]
+    DuplicatePriceSource,
+    #[msg("Price source is not a Pyth price update or fallback price for this feed.")]
+    InvalidPriceSource,
+    #[msg("Minimum price source count must be between 1 and 5.")]
+    InvalidPriceSourceCount,
+    #[msg("Arithmetic overflow.")]
+    MathOverflow,
+    #[msg("The manual price has expired.")]
+    ManualPriceExpired,
+    #[msg("Manual price duration must be between 1 second and 24 hours.")]
+    InvalidManualPriceDuration,
+    #[msg("Invalid vesting schedule.")]
+    InvalidVestingSchedule,
+    #[msg("A vesting position account is required in vesting mode.")]
+    VestingPositionRequired,
+    #[msg("The vesting schedule changed since this position was created.")]
+    VestingScheduleMismatch,
+    #[msg("Nothing to claim.")]
+    NothingToClaim,
+    #[msg("An allocation account is required in deferred claim mode.")]
+    AllocationRequired,
+    #[msg("TGE has not been reached yet.")]
+    TgeNotReached,
+    #[msg("TGE has already started and can no longer be changed.")]
+    TgeAlreadyStarted,
+    #[msg("TGE timestamp must be positive.")]
+    InvalidTgeTimestamp,
+    #[msg("Soft cap must be positive, not above the hard cap, and the sale end must be in the future.")]
+    InvalidSoftCap,
+    #[msg("The soft cap can only be set before the first purchase.")]
+    SaleAlreadyStarted,
+    #[msg("Soft cap mode is not enabled.")]
+    SoftCapNotSet,
+    #[msg("Soft cap mode requires deferred claim delivery.")]
+    SoftCapRequiresDeferredClaim,
+    #[msg("The sale is closed.")]
+    SaleClosed,
+    #[msg("The sale has not ended yet.")]
+    SaleNotEnded,
+    #[msg("The sale has already been finalized.")]
+    SaleAlreadyFinalized,
+    #[msg("The sale did not reach its soft cap.")]
+    SaleNotSucceeded,
+    #[msg("Refunds are only available after a failed sale.")]
+    SaleNotFailed,
+    #[msg("An escrow account is required in soft cap mode.")]
+    EscrowRequired,
+    #[msg("A token account is required to receive this refund.")]
+    RefundAccountRequired,
+    #[msg("The hard cap has been reached.")]
+    HardCapReached,
+    #[msg("The purchase would exceed the hard cap.")]
+    HardCapExceeded,
+    #[msg("The hard cap must not be below the soft cap.")]
+    InvalidHardCap,
+    #[msg("Invalid sale round configuration.")]
+    InvalidSaleRoundConfig,
+    #[msg("A sale round account is required when buying in a round.")]
+    SaleRoundRequired,
+    #[msg("The sale round account does not match the round index.")]
+    InvalidSaleRound,
+    #[msg("The sale round is not active.")]
+    RoundNotActive,
+    #[msg("The wallet is not on the allowlist for this round.")]
+    NotAllowlisted,
+    #[msg("A round purchase account is required when buying in a round.")]
+    RoundPurchaseRequired,
+    #[msg("The purchase exceeds the round allocation or per-wallet cap.")]
+    RoundLimitExceeded,
+    #[msg("Sale rounds cannot be used in soft cap mode.")]
+    RoundsUnavailableWithSoftCap,
+    #[msg("Pricing tiers must have positive supply and price, up to 8 tiers.")]
+    InvalidPricingTiers,
+    #[msg("Price steps must have positive prices and increasing timestamps, up to 8 steps.")]
+    InvalidPriceSchedule,
+    #[msg("Pricing tiers and a time-based price schedule cannot be used together.")]
+    PricingModeConflict,
+    #[msg("Invalid Dutch auction configuration.")]
+    InvalidDutchAuction,
+    #[msg("The Dutch auction already has purchases and can no longer be changed.")]
+    AuctionAlreadyStarted,
+    #[msg("An auction bid account is required in a uniform clearing Dutch auction.")]
+    AuctionBidRequired,
+    #[msg("Rebates are only available in a uniform clearing Dutch auction.")]
+    AuctionNotUniform,
+    #[msg("The Dutch auction has not ended yet.")]
+    AuctionNotEnded,
+    #[msg("Invalid bonding curve configuration.")]
+    InvalidBondingCurve,
+    #[msg("An allowlist proof is required.")]
+    MerkleProofRequired,
+    #[msg("The allowlist proof is invalid.")]
+    InvalidMerkleProof,
+    #[msg("The purchase exceeds the wallet's allowlist allocation.")]
+    WalletAllocationExceeded,
+    #[msg("Invalid KYC configuration.")]
+    InvalidKycConfig,
+    #[msg("A KYC attestation is required.")]
+    KycAttestationRequired,
+    #[msg("The KYC attestation has expired.")]
+    KycAttestationExpired,
+    #[msg("Unknown KYC tier.")]
+    InvalidKycTier,
+    #[msg("The KYC attestation signature is missing or invalid.")]
+    InvalidKycAttestation,
+    #[msg("The purchase exceeds the wallet's KYC tier limit.")]
+    KycLimitExceeded,
+    #[msg("This wallet is blocked from purchasing.")]
+    WalletBlocked,
+    #[msg("A gating token account is required.")]
+    GatingTokenAccountRequired,
+    #[msg("The gating token account has the wrong mint or owner.")]
+    InvalidGatingTokenAccount,
+    #[msg("Gating token balance is below the required minimum.")]
+    InsufficientGatingBalance,
+    #[msg("The purchase exceeds the allocation granted by the gating token balance.")]
+    GatingAllocationExceeded,
+    #[msg("Buyers cannot refer themselves.")]
+    SelfReferral,
+    #[msg("Referral reward must not exceed 10000 bps.")]
+    InvalidReferralReward,
+    #[msg("Invalid promo code configuration.")]
+    InvalidPromoCodeConfig,
+    #[msg("The promo code and its account must be passed together.")]
+    PromoCodeRequired,
+    #[msg("The promo code does not match.")]
+    InvalidPromoCode,
+    #[msg("The promo code has expired.")]
+    PromoCodeExpired,
+    #[msg("The promo code has no uses left.")]
+    PromoCodeExhausted,
+    #[msg("Invalid volume bonus tiers.")]
+    InvalidVolumeBonuses,
+    #[msg("Purchasing for another recipient is not available in soft cap mode.")]
+    RecipientUnavailableWithSoftCap,
+    #[msg("Batch purchases require immediate delivery without soft cap, uniform auction settlement or wallet-level limits.")]
+    BatchPurchaseUnavailable,
+    #[msg("Invalid batch: recipient count or remaining accounts do not match.")]
+    InvalidBatch,
+    #[msg("Invalid batch recipient accounts.")]
+    InvalidBatchRecipient,
+    #[msg("client_order_id and the order account must be passed together.")]
+    OrderAccountRequired,
+    #[msg("Dutch auction purchases are only accepted between start_time and end_time.")]
+    AuctionNotActive,
+    #[msg("The oracle is marked down: a manual price account is required.")]
+    ManualPriceRequired,
+    #[msg("Purchases must name an active sale round.")]
+    RoundIndexRequired,
+    #[msg("The same recipient appears more than once in the batch.")]
+    DuplicateBatchRecipient,
+    #[msg("The vesting schedule cannot change after the first vesting purchase.")]
+    VestingScheduleLocked,
 }
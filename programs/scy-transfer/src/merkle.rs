@@ -0,0 +1,132 @@
+// Merkle 白名单工具：链上用 verify 校验 (wallet, max_allocation) 的 proof，
+// 链下（测试、客户端工具）可以用 parse_csv 和 MerkleTree 从 CSV 构建树、计算 root 和每个钱包的 proof
+//
+// 叶子 = sha256(0x00 || wallet || max_allocation 小端序)，父节点 = sha256(0x01 || 较小的子节点 || 较大的子节点)，
+// 子节点排序后再哈希，proof 中不需要记录左右方向；某一层节点数为奇数时，最后一个节点直接提升到上一层
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+// 计算 (wallet, max_allocation) 对应的叶子哈希
+pub fn leaf_hash(wallet: &Pubkey, max_allocation: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &max_allocation.to_le_bytes()]).to_bytes()
+}
+
+// 两个子节点排序后计算父节点哈希
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, low, high]).to_bytes()
+}
+
+// 校验 leaf 沿 proof 逐层哈希后是否等于 root
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+// 解析 "wallet,max_allocation" 格式的 CSV，忽略空行、# 开头的注释行以及 wallet 列的表头
+pub fn parse_csv(csv: &str) -> Result<Vec<(Pubkey, u64)>, String> {
+    let mut entries = Vec::new();
+    for (line_number, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.to_ascii_lowercase().starts_with("wallet") {
+            continue;
+        }
+
+        let (wallet, max_allocation) = line
+            .split_once(',')
+            .ok_or_else(|| format!("line {}: expected wallet,max_allocation", line_number + 1))?;
+        let wallet = Pubkey::from_str(wallet.trim())
+            .map_err(|err| format!("line {}: invalid wallet: {}", line_number + 1, err))?;
+        let max_allocation = max_allocation
+            .trim()
+            .parse::<u64>()
+            .map_err(|err| format!("line {}: invalid max_allocation: {}", line_number + 1, err))?;
+        entries.push((wallet, max_allocation));
+    }
+    Ok(entries)
+}
+
+// 按层保存的 Merkle 树，layers[0] 为叶子，最后一层为 root
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers.last().is_some_and(|layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| if pair.len() == 2 { hash_pair(&pair[0], &pair[1]) } else { pair[0] })
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers }
+    }
+
+    // 由 (wallet, max_allocation) 列表构建，叶子顺序与列表顺序一致
+    pub fn from_entries(entries: &[(Pubkey, u64)]) -> Self {
+        Self::new(entries.iter().map(|(wallet, max_allocation)| leaf_hash(wallet, *max_allocation)).collect())
+    }
+
+    // 树的 root，空树为全 0（即关闭白名单）
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().and_then(|layer| layer.first().copied()).unwrap_or_default()
+    }
+
+    // 第 index 个叶子的 proof，从叶子所在层向上排列
+    pub fn proof(&self, mut index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_verifiable_proofs_from_csv() {
+        let wallets: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let csv = std::iter::once("wallet,max_allocation".to_string())
+            .chain(wallets.iter().enumerate().map(|(i, wallet)| format!("{},{}", wallet, (i + 1) * 1000)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let entries = parse_csv(&csv).unwrap();
+        assert_eq!(entries.len(), 5);
+        let tree = MerkleTree::from_entries(&entries);
+        let root = tree.root();
+
+        for (i, (wallet, max_allocation)) in entries.iter().enumerate() {
+            let proof = tree.proof(i).unwrap();
+            assert!(verify(&root, leaf_hash(wallet, *max_allocation), &proof));
+            // 修改额度后 proof 失效
+            assert!(!verify(&root, leaf_hash(wallet, max_allocation + 1), &proof));
+        }
+        assert!(tree.proof(5).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_csv() {
+        assert!(parse_csv("not-a-wallet,100").is_err());
+        assert!(parse_csv(&format!("{}", Pubkey::new_unique())).is_err());
+    }
+}
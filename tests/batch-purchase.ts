@@ -0,0 +1,172 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_USD_FEED_ID,
+  USDC_MINT,
+  setupLocalSale,
+  setPriceUpdate,
+  setTokenAccount,
+  now,
+  tokenBalance,
+  findPda,
+  scy,
+} from "./helpers";
+
+// 批量购买：合作方一次付款，为多个接收人购买 SCY，按总数一次计价，任一接收人无效时整笔失败
+describe("scy-transfer batch purchases", () => {
+  let sale: LocalSale;
+  let recipients: PublicKey[];
+  const bn = (amount: bigint) => new anchor.BN(amount.toString());
+
+  const payerUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const recipientScy = (wallet: PublicKey) =>
+    getAssociatedTokenAddressSync(sale.scyMint, wallet);
+
+  const buyerInfoPda = (wallet: PublicKey) =>
+    findPda(sale.program, Buffer.from("buyer"), wallet.toBuffer());
+
+  // 每个接收人依次传入 (recipient, recipient 的 SCY ATA, recipient 的拒绝名单 PDA, recipient 的 BuyerInfo PDA)
+  const recipientAccounts = (wallets: PublicKey[]) =>
+    wallets.flatMap((wallet) => [
+      { pubkey: wallet, isSigner: false, isWritable: false },
+      { pubkey: recipientScy(wallet), isSigner: false, isWritable: true },
+      {
+        pubkey: findPda(sale.program, Buffer.from("denied"), wallet.toBuffer()),
+        isSigner: false,
+        isWritable: false,
+      },
+      { pubkey: buyerInfoPda(wallet), isSigner: false, isWritable: true },
+    ]);
+
+  // 同一份价格更新只计入一次，额外的价格源需要使用不同的 publish_time
+  const freshUsdcPrice = async (age = 0) =>
+    setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      USDC_USD_FEED_ID,
+      BigInt(1e8),
+      -8,
+      (await now(sale.context)) - BigInt(age)
+    );
+
+  // 额外的价格源排在接收人账户之前，数量通过 price_source_count 传入
+  const buyBatch = async (wallets: PublicKey[], amounts: bigint[], priceSources: PublicKey[] = []) => {
+    const priceUpdate = await freshUsdcPrice();
+    return sale.program.methods
+      .buyBatchWithSpl(amounts.map(bn), priceSources.length, null)
+      .accounts({
+        user: sale.buyer.publicKey,
+        userTokenAta: payerUsdcAccount(),
+        userMint: USDC_MINT,
+        mint: sale.scyMint,
+        priceUpdate,
+        manualPrice: null,
+      })
+      .remainingAccounts([
+        ...priceSources.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })),
+        ...recipientAccounts(wallets),
+      ])
+      .signers([sale.buyer])
+      .rpc();
+  };
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("batch should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    recipients = [0, 1, 2].map(() => Keypair.generate().publicKey);
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Charges the total once and delivers each recipient's amount", async () => {
+    const amounts = [scy(500), scy(1_000), scy(2_500)];
+    await buyBatch(recipients, amounts);
+
+    for (const [i, wallet] of recipients.entries()) {
+      assert.equal(await tokenBalance(sale.context, recipientScy(wallet)), amounts[i]);
+    }
+    // 4000 SCY * 0.02 USD = 80 USDC
+    assert.equal(
+      await tokenBalance(sale.context, payerUsdcAccount()),
+      BigInt(1e9) - BigInt(80 * 1e6)
+    );
+  });
+
+  it("Records each recipient's purchase history", async () => {
+    await buyBatch(recipients, [scy(500), scy(1_000), scy(2_500)]);
+    await buyBatch(recipients.slice(0, 1), [scy(500)]);
+
+    const info = await sale.program.account.buyerInfo.fetch(buyerInfoPda(recipients[0]));
+    assert.isTrue(info.wallet.equals(recipients[0]));
+    assert.equal(info.purchaseCount.toNumber(), 2);
+    assert.equal(info.splPurchased.toString(), scy(1_000).toString());
+    assert.equal(info.usdSpent.toNumber(), 20 * 1e6);
+
+    const stats = await sale.program.account.saleStats.fetch(findPda(sale.program, Buffer.from("sale_stats")));
+    assert.equal(stats.buyerCount.toNumber(), 3);
+    assert.equal(stats.splSold.toString(), scy(4_500).toString());
+    assert.equal(stats.usdRaised.toNumber(), 90 * 1e6);
+  });
+
+  it("Rejects a recipient listed twice", async () => {
+    await expectError(
+      buyBatch([recipients[0], recipients[1], recipients[0]], [scy(500), scy(500), scy(500)]),
+      /DuplicateBatchRecipient/
+    );
+  });
+
+  it("Rejects batches while sale rounds are required", async () => {
+    await sale.program.methods
+      .setRoundRequired(true)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await expectError(buyBatch(recipients, [scy(500), scy(500), scy(500)]), /RoundIndexRequired/);
+  });
+
+  it("Fails atomically when any recipient is invalid", async () => {
+    // 第二个接收人低于单笔最低购买数量
+    await expectError(
+      buyBatch(recipients, [scy(500), scy(10), scy(500)]),
+      /PurchaseAmountTooLow/
+    );
+
+    // 第三个接收人在拒绝名单中
+    await sale.program.methods
+      .addToDenylist(recipients[2])
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await expectError(
+      buyBatch(recipients, [scy(500), scy(500), scy(500)]),
+      /WalletBlocked/
+    );
+    assert.isNull(await sale.context.banksClient.getAccount(recipientScy(recipients[0])));
+    assert.equal(await tokenBalance(sale.context, payerUsdcAccount()), BigInt(1e9));
+  });
+
+  it("Rejects mismatched recipient accounts", async () => {
+    await expectError(buyBatch(recipients.slice(0, 2), [scy(500), scy(500), scy(500)]), /InvalidBatch/);
+  });
+
+  it("Counts extra price sources like a single purchase", async () => {
+    await sale.program.methods
+      .setMinPriceSources(2)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await expectError(buyBatch(recipients, [scy(500), scy(500), scy(500)]), /NotEnoughPriceSources/);
+
+    await buyBatch(recipients, [scy(500), scy(500), scy(500)], [await freshUsdcPrice(1)]);
+    assert.equal(await tokenBalance(sale.context, recipientScy(recipients[2])), scy(500));
+  });
+});
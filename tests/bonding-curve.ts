@@ -0,0 +1,85 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  SCY_DECIMALS,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 联合曲线：价格是累计售出数量的函数，花费按曲线积分计算
+describe("scy-transfer bonding curve", () => {
+  let sale: LocalSale;
+  const MILLION = 1_000_000;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const quote = async (amount: anchor.BN) =>
+    BigInt(
+      (
+        await sale.program.methods
+          .quote(amount)
+          .accounts({ mint: sale.scyMint })
+          .view()
+      ).toString()
+    );
+
+  const useCurve = async (exponential: boolean, doublingSupply: bigint = scy(MILLION)) => {
+    await sale.program.methods
+      .setBondingCurve({
+        kind: exponential ? { exponential: {} } : { linear: {} },
+        basePrice: new anchor.BN(20_000), // 0.02 USD
+        slopePerMillion: new anchor.BN(10_000), // 每售出 100 万 SCY 涨 0.01 USD
+        doublingSupply: new anchor.BN(doublingSupply.toString()),
+      })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await sale.program.methods
+      .setPricingMode({ bondingCurve: {} })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e11));
+  });
+
+  it("Integrates a linear curve over the purchased range", async () => {
+    await useCurve(false);
+    // 价格从 0.02 线性涨到 0.03，平均 0.025 USD，100 万 SCY 共 25000 USD
+    assert.equal(await quote(usd(25_000)), scy(MILLION));
+
+    await buyWithUsdc(sale, usd(25_000));
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(MILLION));
+
+    // 下一笔从 0.03 USD 开始计价
+    const next = Number(await quote(usd(30))) / 10 ** SCY_DECIMALS;
+    assert.isBelow(next, 1_000);
+    assert.approximately(next, 1_000, 1);
+  });
+
+  it("Integrates an exponential curve over the purchased range", async () => {
+    await useCurve(true);
+    // 第一个翻倍区间价格从 0.02 涨到 0.04，平均 0.03 USD
+    assert.equal(await quote(usd(30_000)), scy(MILLION));
+  });
+
+  it("Fails instead of under-filling when the curve math overflows", async () => {
+    // 每个最小单位翻倍一次，积分在二分查找中溢出，必须报错而不是按买不起处理
+    await useCurve(true, BigInt(1));
+    try {
+      await quote(usd(10));
+      assert.fail("quote should fail on overflow");
+    } catch (error) {
+      assert.include(String(error), "MathOverflow");
+    }
+  });
+});
@@ -0,0 +1,68 @@
+import { Keypair } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  findPda,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 拒绝名单：管理员为钱包创建 Denied PDA 后，该钱包无法购买，移除后恢复
+describe("scy-transfer denylist", () => {
+  let sale: LocalSale;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const deniedPda = () =>
+    findPda(sale.program, Buffer.from("denied"), sale.buyer.publicKey.toBuffer());
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    await sale.program.methods
+      .addToDenylist(sale.buyer.publicKey)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Rejects purchases from a denied wallet", async () => {
+    try {
+      await buyWithUsdc(sale, usd(10));
+      assert.fail("purchase should be rejected");
+    } catch (err) {
+      assert.match(String(err), /WalletBlocked/);
+    }
+  });
+
+  it("Allows purchases again after the wallet is removed", async () => {
+    await sale.program.methods
+      .removeFromDenylist()
+      .accounts({ denied: deniedPda(), admin: sale.admin.publicKey })
+      .rpc();
+    assert.isNull(await sale.context.banksClient.getAccount(deniedPda()));
+
+    await buyWithUsdc(sale, usd(10));
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(500));
+  });
+
+  it("Only lets the admin manage the denylist", async () => {
+    const outsider = Keypair.generate();
+    try {
+      await sale.program.methods
+        .removeFromDenylist()
+        .accounts({ denied: deniedPda(), admin: outsider.publicKey })
+        .signers([outsider])
+        .rpc();
+      assert.fail("non-admin should be rejected");
+    } catch (err) {
+      assert.match(String(err), /Unauthorized/);
+    }
+  });
+});
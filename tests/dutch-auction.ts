@@ -0,0 +1,166 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  SCY_DECIMALS,
+  setupLocalSale,
+  setTokenAccount,
+  now,
+  warpTo,
+  tokenBalance,
+  buyWithUsdc,
+} from "./helpers";
+
+// 荷兰拍：价格从 0.04 USD 在 1000 秒内衰减到 0.02 USD
+describe("scy-transfer dutch auction", () => {
+  let sale: LocalSale;
+  let start: bigint;
+  const usdcBalance = BigInt(1e9);
+  const tenUsd = new anchor.BN(10 * 1e6);
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const quoteTenUsd = async () =>
+    Number(
+      await sale.program.methods
+        .quote(tenUsd)
+        .accounts({ mint: sale.scyMint })
+        .view()
+    ) / 10 ** SCY_DECIMALS;
+
+  const startAuction = async (exponential: boolean, uniformClearing: boolean) => {
+    await sale.program.methods
+      .setDutchAuction({
+        startPrice: new anchor.BN(40_000),
+        floorPrice: new anchor.BN(20_000),
+        startTime: new anchor.BN(start.toString()),
+        endTime: new anchor.BN((start + BigInt(1000)).toString()),
+        decay: exponential ? { exponential: {} } : { linear: {} },
+        halfLife: new anchor.BN(100),
+        uniformClearing,
+      })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await sale.program.methods
+      .setPricingMode({ dutchAuction: {} })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, usdcBalance);
+    start = await now(sale.context);
+  });
+
+  it("Decays the price linearly to the floor", async () => {
+    await startAuction(false, false);
+    assert.approximately(await quoteTenUsd(), 250, 0.001); // 0.04 USD
+
+    await warpTo(sale.context, start + BigInt(500));
+    assert.approximately(await quoteTenUsd(), 10 / 0.03, 0.001);
+
+    await warpTo(sale.context, start + BigInt(2000));
+    assert.approximately(await quoteTenUsd(), 500, 0.001); // 最低价 0.02 USD
+  });
+
+  it("Halves the distance to the floor every half-life", async () => {
+    await startAuction(true, false);
+    await warpTo(sale.context, start + BigInt(100));
+    assert.approximately(await quoteTenUsd(), 10 / 0.03, 0.001);
+
+    await warpTo(sale.context, start + BigInt(200));
+    assert.approximately(await quoteTenUsd(), 10 / 0.025, 0.001);
+  });
+
+  it("Rejects purchases outside the auction window", async () => {
+    start = start + BigInt(100);
+    await startAuction(false, false);
+    try {
+      await buyWithUsdc(sale, tenUsd);
+      assert.fail("purchase should fail before the auction starts");
+    } catch (error) {
+      assert.include(String(error), "AuctionNotActive");
+    }
+
+    await warpTo(sale.context, start);
+    await buyWithUsdc(sale, tenUsd);
+
+    await warpTo(sale.context, start + BigInt(1000));
+    try {
+      await buyWithUsdc(sale, tenUsd);
+      assert.fail("purchase should fail after the auction ends");
+    } catch (error) {
+      assert.include(String(error), "AuctionNotActive");
+    }
+  });
+
+  it("Rebates earlier buyers down to the clearing price", async () => {
+    await startAuction(false, true);
+    await buyWithUsdc(sale, tenUsd); // 按 0.04 USD 买入 250 SCY
+    await warpTo(sale.context, start + BigInt(500));
+    await buyWithUsdc(sale, tenUsd); // 按 0.03 USD 买入
+
+    try {
+      await sale.program.methods
+        .claimAuctionRebate()
+        .accounts({ owner: sale.buyer.publicKey, userUsdcAta: userUsdcAccount(), userUsdtAta: null })
+        .signers([sale.buyer])
+        .rpc();
+      assert.fail("rebate should not be claimable before the auction ends");
+    } catch (error) {
+      assert.include(String(error), "AuctionNotEnded");
+    }
+
+    await warpTo(sale.context, start + BigInt(1000));
+    await sale.program.methods
+      .claimAuctionRebate()
+      .accounts({ owner: sale.buyer.publicKey, userUsdcAta: userUsdcAccount(), userUsdtAta: null })
+      .signers([sale.buyer])
+      .rpc();
+
+    // 第一笔按最终成交价 0.03 USD 只需支付 7.5 USDC，返还 2.5 USDC（向合约有利的方向取整）
+    const balance = await tokenBalance(sale.context, userUsdcAccount());
+    assert.approximately(
+      Number(balance - (usdcBalance - BigInt(20 * 1e6))),
+      2.5 * 1e6,
+      1
+    );
+  });
+
+  it("Refunds a failed soft-cap auction once, without a rebate", async () => {
+    await sale.program.methods
+      .setSoftCap(new anchor.BN(1_000_000 * 1e6), new anchor.BN((start + BigInt(1000)).toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await startAuction(false, true);
+    await buyWithUsdc(sale, tenUsd);
+    await warpTo(sale.context, start + BigInt(500));
+    await buyWithUsdc(sale, tenUsd);
+
+    await warpTo(sale.context, start + BigInt(1000));
+    await sale.program.methods.finalizeSale().rpc();
+
+    // 募资失败时差价不能领取，否则退款时会再次退回同一部分付款
+    try {
+      await sale.program.methods
+        .claimAuctionRebate()
+        .accounts({ owner: sale.buyer.publicKey, userUsdcAta: userUsdcAccount(), userUsdtAta: null })
+        .signers([sale.buyer])
+        .rpc();
+      assert.fail("rebate should not be claimable after a failed sale");
+    } catch (error) {
+      assert.include(String(error), "SaleNotSucceeded");
+    }
+
+    await sale.program.methods
+      .claimRefund()
+      .accounts({ owner: sale.buyer.publicKey, userUsdcAta: userUsdcAccount(), userUsdtAta: null })
+      .signers([sale.buyer])
+      .rpc();
+    assert.equal(await tokenBalance(sale.context, userUsdcAccount()), usdcBalance);
+  });
+});
@@ -0,0 +1,88 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  findPda,
+  buildMerkleTree,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 代为购买：付款方（托管方 / 支付合作方）为客户钱包购买 SCY，SCY 和钱包额度都记在 recipient 名下
+describe("scy-transfer gift purchases", () => {
+  let sale: LocalSale;
+  let recipient: PublicKey;
+
+  const buyFor = (
+    wallet: PublicKey,
+    amount: anchor.BN,
+    allowlistProof: { maxAllocation: anchor.BN; proof: number[][] } | null = null
+  ) => buyWithUsdc(sale, amount, { allowlistProof, accounts: { recipient: wallet } });
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("purchase should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    recipient = Keypair.generate().publicKey;
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Delivers SCY to the recipient's ATA and tracks the recipient", async () => {
+    await buyFor(recipient, usd(10));
+
+    const recipientScy = getAssociatedTokenAddressSync(sale.scyMint, recipient);
+    assert.equal(await tokenBalance(sale.context, recipientScy), scy(500));
+
+    const buyerInfo = await sale.program.account.buyerInfo.fetch(
+      findPda(sale.program, Buffer.from("buyer"), recipient.toBuffer())
+    );
+    assert.isTrue(buyerInfo.wallet.equals(recipient));
+    assert.equal(buyerInfo.splPurchased.toString(), scy(500).toString());
+    // 付款方没有自己的购买记录
+    assert.isNull(
+      await sale.context.banksClient.getAccount(
+        findPda(sale.program, Buffer.from("buyer"), sale.buyer.publicKey.toBuffer())
+      )
+    );
+  });
+
+  it("Checks wallet allocations against the recipient", async () => {
+    const tree = buildMerkleTree([
+      [recipient, scy(500)],
+      [sale.buyer.publicKey, scy(5_000)],
+    ]);
+    await sale.program.methods
+      .setMerkleRoot(tree.root)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    const recipientProof = {
+      maxAllocation: new anchor.BN(scy(500).toString()),
+      proof: tree.proof(0),
+    };
+
+    await buyFor(recipient, usd(10), recipientProof);
+    await expectError(buyFor(recipient, usd(10), recipientProof), /WalletAllocationExceeded/);
+  });
+
+  it("Rejects a denied recipient", async () => {
+    await sale.program.methods
+      .addToDenylist(recipient)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await expectError(buyFor(recipient, usd(10)), /WalletBlocked/);
+  });
+});
@@ -0,0 +1,80 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  SCY_DECIMALS,
+  setupLocalSale,
+  setTokenAccount,
+  findPda,
+  tokenBalance,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 累计募资硬顶：超过硬顶的购买按剩余额度部分成交，或直接拒绝
+describe("scy-transfer hard cap", () => {
+  let sale: LocalSale;
+  const usdcBalance = BigInt(1_000_000_000); // 1000 USDC
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const setHardCap = (hardCapUsd: number, partialFill: boolean) =>
+    sale.program.methods
+      .setHardCap(new anchor.BN(hardCapUsd), partialFill)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, usdcBalance);
+  });
+
+  it("Partially fills a purchase that crosses the hard cap", async () => {
+    await setHardCap(150 * 1e6, true);
+    await buyWithUsdc(sale, usd(100));
+    await buyWithUsdc(sale, usd(100));
+
+    // 第二笔只收取剩余的 50 USDC
+    assert.equal(
+      await tokenBalance(sale.context, userUsdcAccount()),
+      usdcBalance - BigInt(150 * 1e6)
+    );
+    assert.equal(
+      await tokenBalance(sale.context, userScyAccount()),
+      BigInt(150 / 0.02) * BigInt(10 ** SCY_DECIMALS)
+    );
+    const stats = await sale.program.account.saleStats.fetch(
+      findPda(sale.program, Buffer.from("sale_stats"))
+    );
+    assert.equal(stats.usdRaised.toNumber(), 150 * 1e6);
+
+    try {
+      await buyWithUsdc(sale, usd(100));
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "HardCapReached");
+    }
+  });
+
+  it("Rejects a purchase that crosses the hard cap when partial fills are off", async () => {
+    await setHardCap(150 * 1e6, false);
+    await buyWithUsdc(sale, usd(100));
+
+    try {
+      await buyWithUsdc(sale, usd(100));
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "HardCapExceeded");
+    }
+    assert.equal(
+      await tokenBalance(sale.context, userUsdcAccount()),
+      usdcBalance - BigInt(100 * 1e6)
+    );
+  });
+});
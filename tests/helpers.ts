@@ -0,0 +1,386 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { ScyTransfer } from "../target/types/scy_transfer";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  MINT_SIZE,
+  ACCOUNT_SIZE,
+  MintLayout,
+  AccountLayout,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { startAnchor, ProgramTestContext, Clock } from "solana-bankrun";
+import { BankrunProvider } from "anchor-bankrun";
+import { createHash } from "crypto";
+
+// 本地测试（solana-bankrun）共用的工具函数：构造 Mint / Token / Pyth 价格账户，并完成合约的初始化
+
+export const PYTH_RECEIVER_PROGRAM_ID = new PublicKey(
+  "rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ"
+);
+
+// 合约中写死的 feed id 和 USDC/USDT Mint 地址
+export const SOL_USD_FEED_ID = Buffer.from(
+  "ef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d",
+  "hex"
+);
+export const USDC_USD_FEED_ID = Buffer.from(
+  "eaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a",
+  "hex"
+);
+export const USDC_MINT = new PublicKey(
+  "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+);
+export const USDT_MINT = new PublicKey(
+  "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"
+);
+
+export const SCY_DECIMALS = 6;
+export const LAMPORTS_PER_SOL = 1_000_000_000;
+
+// 以 SCY 最小单位表示的数量，以及以 micro-USD 表示的金额
+export const scy = (amount: number) =>
+  BigInt(amount) * BigInt(10 ** SCY_DECIMALS);
+export const usd = (amount: number) => new anchor.BN(amount * 1e6);
+
+export type LocalSale = {
+  context: ProgramTestContext;
+  provider: BankrunProvider;
+  program: Program<ScyTransfer>;
+  admin: Keypair;
+  buyer: Keypair;
+  scyMint: PublicKey;
+};
+
+export function findPda(program: Program<ScyTransfer>, ...seeds: Buffer[]) {
+  return PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+}
+
+// 直接写入一个 SPL Mint 账户
+export function setMint(
+  context: ProgramTestContext,
+  address: PublicKey,
+  authority: PublicKey,
+  decimals: number
+) {
+  const data = Buffer.alloc(MINT_SIZE);
+  MintLayout.encode(
+    {
+      mintAuthorityOption: 1,
+      mintAuthority: authority,
+      supply: BigInt(0),
+      decimals,
+      isInitialized: true,
+      freezeAuthorityOption: 0,
+      freezeAuthority: PublicKey.default,
+    },
+    data
+  );
+  context.setAccount(address, {
+    lamports: 1_000_000_000,
+    data,
+    owner: TOKEN_PROGRAM_ID,
+    executable: false,
+  });
+}
+
+// 直接写入一个带余额的 SPL Token 账户（默认写在 owner 的 ATA 地址上）
+export function setTokenAccount(
+  context: ProgramTestContext,
+  mint: PublicKey,
+  owner: PublicKey,
+  amount: bigint,
+  address: PublicKey = getAssociatedTokenAddressSync(mint, owner, true)
+) {
+  const data = Buffer.alloc(ACCOUNT_SIZE);
+  AccountLayout.encode(
+    {
+      mint,
+      owner,
+      amount,
+      delegateOption: 0,
+      delegate: PublicKey.default,
+      state: 1,
+      isNativeOption: 0,
+      isNative: BigInt(0),
+      delegatedAmount: BigInt(0),
+      closeAuthorityOption: 0,
+      closeAuthority: PublicKey.default,
+    },
+    data
+  );
+  context.setAccount(address, {
+    lamports: 1_000_000_000,
+    data,
+    owner: TOKEN_PROGRAM_ID,
+    executable: false,
+  });
+  return address;
+}
+
+// 按 pyth-solana-receiver-sdk 中 PriceUpdateV2 的布局构造价格账户（VerificationLevel::Full）
+export function setPriceUpdate(
+  context: ProgramTestContext,
+  address: PublicKey,
+  feedId: Buffer,
+  price: bigint,
+  exponent: number,
+  publishTime: bigint,
+  emaPrice: bigint = price
+) {
+  const data = Buffer.alloc(8 + 32 + 2 + 84 + 8);
+  let offset = 0;
+  createHash("sha256")
+    .update("account:PriceUpdateV2")
+    .digest()
+    .copy(data, offset, 0, 8);
+  offset += 8;
+  offset += 32; // write_authority
+  data.writeUInt8(1, offset); // VerificationLevel::Full
+  offset += 1;
+  feedId.copy(data, offset);
+  offset += 32;
+  data.writeBigInt64LE(price, offset);
+  offset += 8;
+  data.writeBigUInt64LE(BigInt(0), offset); // conf
+  offset += 8;
+  data.writeInt32LE(exponent, offset);
+  offset += 4;
+  data.writeBigInt64LE(publishTime, offset);
+  offset += 8;
+  data.writeBigInt64LE(publishTime, offset); // prev_publish_time
+  offset += 8;
+  data.writeBigInt64LE(emaPrice, offset);
+  offset += 8;
+  data.writeBigUInt64LE(BigInt(0), offset); // ema_conf
+  context.setAccount(address, {
+    lamports: 1_000_000_000,
+    data,
+    owner: PYTH_RECEIVER_PROGRAM_ID,
+    executable: false,
+  });
+  return address;
+}
+
+export async function now(context: ProgramTestContext): Promise<bigint> {
+  return (await context.banksClient.getClock()).unixTimestamp;
+}
+
+// 把本地 bank 的时间调整到 timestamp（秒）
+export async function warpTo(context: ProgramTestContext, timestamp: bigint) {
+  const clock = await context.banksClient.getClock();
+  context.setClock(
+    new Clock(
+      clock.slot,
+      clock.epochStartTimestamp,
+      clock.epoch,
+      clock.leaderScheduleEpoch,
+      timestamp
+    )
+  );
+}
+
+export async function tokenBalance(
+  context: ProgramTestContext,
+  address: PublicKey
+): Promise<bigint> {
+  const account = await context.banksClient.getAccount(address);
+  return AccountLayout.decode(account.data).amount;
+}
+
+// 启动本地 bank，初始化 state 及各个 PDA 账户，并向 pda_spl_ata 存入 SCY
+export async function setupLocalSale(
+  depositAmount: bigint = BigInt(100_000_000) * BigInt(10 ** SCY_DECIMALS)
+): Promise<LocalSale> {
+  const context = await startAnchor(".", [], []);
+  const provider = new BankrunProvider(context);
+  anchor.setProvider(provider);
+  const program = new Program<ScyTransfer>(
+    anchor.workspace.ScyTransfer.idl,
+    provider
+  );
+
+  const admin = context.payer;
+  const buyer = Keypair.generate();
+  context.setAccount(buyer.publicKey, {
+    lamports: 100 * LAMPORTS_PER_SOL,
+    data: Buffer.alloc(0),
+    owner: SystemProgram.programId,
+    executable: false,
+  });
+
+  const scyMint = Keypair.generate().publicKey;
+  setMint(context, scyMint, admin.publicKey, SCY_DECIMALS);
+  setMint(context, USDC_MINT, admin.publicKey, 6);
+  setMint(context, USDT_MINT, admin.publicKey, 6);
+  setTokenAccount(context, scyMint, admin.publicKey, depositAmount);
+
+  await program.methods
+    .initializeState(USDC_MINT, USDT_MINT, scyMint)
+    .accounts({ admin: admin.publicKey })
+    .rpc();
+  await program.methods
+    .initializePdaSol()
+    .accounts({ admin: admin.publicKey })
+    .rpc();
+  await program.methods
+    .initializePdaSplAta()
+    .accounts({ admin: admin.publicKey, mint: scyMint })
+    .rpc();
+  await program.methods
+    .initializePdaUsdcAta()
+    .accounts({ admin: admin.publicKey, usdcMint: USDC_MINT })
+    .rpc();
+  await program.methods
+    .initializePdaUsdtAta()
+    .accounts({ admin: admin.publicKey, usdtMint: USDT_MINT })
+    .rpc();
+  await program.methods
+    .initializeSaleStats()
+    .accounts({ admin: admin.publicKey })
+    .rpc();
+  await program.methods
+    .deposit(new anchor.BN(depositAmount.toString()))
+    .accounts({ admin: admin.publicKey })
+    .rpc();
+
+  return { context, provider, program, admin, buyer, scyMint };
+}
+
+// 购买参数（对应合约的 PurchaseOptions），以及需要额外传入的账户和指令
+export type BuyOptions = {
+  clientRef?: number[] | null;
+  allowPartial?: boolean;
+  roundIndex?: number | null;
+  allowlistProof?: { maxAllocation: anchor.BN; proof: number[][] } | null;
+  kycAttestation?: { expiry: anchor.BN; tier: number } | null;
+  promoCode?: string | null;
+  clientOrderId?: number[] | null;
+  // 覆盖或补充默认的账户（recipient、referrer、order 等）
+  accounts?: Record<string, PublicKey | null>;
+  // 额外的价格源
+  remainingAccounts?: PublicKey[];
+  preInstructions?: TransactionInstruction[];
+};
+
+// 按合约 PurchaseOptions 的字段填充购买参数，未指定的字段传默认值
+const purchaseOptions = (options: BuyOptions) => ({
+  clientRef: options.clientRef ?? null,
+  allowPartial: options.allowPartial ?? false,
+  roundIndex: options.roundIndex ?? null,
+  allowlistProof: options.allowlistProof ?? null,
+  kycAttestation: options.kycAttestation ?? null,
+  promoCode: options.promoCode ?? null,
+  clientOrderId: options.clientOrderId ?? null,
+});
+
+// 用 buyer 的 USDC 购买 SCY，每次都写入一个新的 USDC/USD 价格账户（1.00 USD，发布时间为当前时间）
+export async function buyWithUsdc(
+  sale: LocalSale,
+  amount: anchor.BN,
+  options: BuyOptions = {}
+) {
+  const priceUpdate = setPriceUpdate(
+    sale.context,
+    Keypair.generate().publicKey,
+    USDC_USD_FEED_ID,
+    BigInt(1e8),
+    -8,
+    await now(sale.context)
+  );
+  return sale.program.methods
+    .buySplWithSpl(amount, purchaseOptions(options))
+    .accounts({
+      user: sale.buyer.publicKey,
+      userTokenAta: getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey),
+      userMint: USDC_MINT,
+      mint: sale.scyMint,
+      priceUpdate,
+      manualPrice: null,
+      ...options.accounts,
+    })
+    .remainingAccounts(
+      (options.remainingAccounts ?? []).map((pubkey) => ({
+        pubkey,
+        isSigner: false,
+        isWritable: false,
+      }))
+    )
+    .preInstructions(options.preInstructions ?? [])
+    .signers([sale.buyer])
+    .rpc();
+}
+
+// 用 buyer 的 SOL 购买 SCY，价格账户由调用方构造
+export async function buyWithSol(
+  sale: LocalSale,
+  lamports: anchor.BN,
+  priceUpdate: PublicKey,
+  options: BuyOptions = {}
+) {
+  return sale.program.methods
+    .buySplWithSol(lamports, purchaseOptions(options))
+    .accounts({
+      user: sale.buyer.publicKey,
+      mint: sale.scyMint,
+      priceUpdate,
+      manualPrice: null,
+      ...options.accounts,
+    })
+    .remainingAccounts(
+      (options.remainingAccounts ?? []).map((pubkey) => ({
+        pubkey,
+        isSigner: false,
+        isWritable: false,
+      }))
+    )
+    .preInstructions(options.preInstructions ?? [])
+    .signers([sale.buyer])
+    .rpc();
+}
+
+// 与合约 merkle 模块一致的白名单 Merkle 树：叶子 = sha256(0x00 || wallet || max_allocation 小端序)，
+// 父节点 = sha256(0x01 || 较小的子节点 || 较大的子节点)，奇数个节点时最后一个直接提升
+const sha256 = (...parts: Buffer[]) =>
+  createHash("sha256").update(Buffer.concat(parts)).digest();
+
+export function merkleLeaf(wallet: PublicKey, maxAllocation: bigint): Buffer {
+  const amount = Buffer.alloc(8);
+  amount.writeBigUInt64LE(maxAllocation);
+  return sha256(Buffer.from([0]), wallet.toBuffer(), amount);
+}
+
+export function buildMerkleTree(entries: [PublicKey, bigint][]) {
+  const layers: Buffer[][] = [entries.map(([wallet, max]) => merkleLeaf(wallet, max))];
+  while (layers[layers.length - 1].length > 1) {
+    const layer = layers[layers.length - 1];
+    const next: Buffer[] = [];
+    for (let i = 0; i < layer.length; i += 2) {
+      if (i + 1 === layer.length) {
+        next.push(layer[i]);
+      } else {
+        const [low, high] = [layer[i], layer[i + 1]].sort(Buffer.compare);
+        next.push(sha256(Buffer.from([1]), low, high));
+      }
+    }
+    layers.push(next);
+  }
+
+  const root = layers[layers.length - 1][0] ?? Buffer.alloc(32);
+  const proof = (index: number) => {
+    const nodes: number[][] = [];
+    for (const layer of layers.slice(0, -1)) {
+      const sibling = index ^ 1;
+      if (sibling < layer.length) nodes.push([...layer[sibling]]);
+      index = Math.floor(index / 2);
+    }
+    return nodes;
+  };
+  return { root: [...root], proof };
+}
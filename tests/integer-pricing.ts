@@ -0,0 +1,56 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  SOL_USD_FEED_ID,
+  USDC_MINT,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setPriceUpdate,
+  setTokenAccount,
+  now,
+  tokenBalance,
+  buyWithUsdc,
+  buyWithSol,
+} from "./helpers";
+
+// 购买数量按整数计算：支付金额先换算成 micro-USD（向下取整），再按 1 SCY = 0.02 USD 换算成 SCY 最小单位
+describe("scy-transfer integer pricing", () => {
+  let sale: LocalSale;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Gives exact amounts where float math rounded down", async () => {
+    // 按 f64 计算 2.3 / 0.02 * 10^6 = 114999999.99...，向下取整会少发 1 个最小单位
+    await buyWithUsdc(sale, new anchor.BN(2_300_000));
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), BigInt(115_000_000));
+
+    await buyWithUsdc(sale, new anchor.BN(19_990_000));
+    assert.equal(
+      await tokenBalance(sale.context, userScyAccount()),
+      BigInt(115_000_000 + 999_500_000)
+    );
+  });
+
+  it("Floors the SOL payment to whole micro-USD before converting", async () => {
+    // 1 SOL * 150.12345678 USD = 150.123456 USD（舍去不足 1 micro-USD 的部分）= 7506.1728 SCY
+    const priceUpdate = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(15_012_345_678),
+      -8,
+      await now(sale.context)
+    );
+    await buyWithSol(sale, new anchor.BN(LAMPORTS_PER_SOL), priceUpdate);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), BigInt(7_506_172_800));
+  });
+});
@@ -0,0 +1,122 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Ed25519Program, Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  now,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// KYC：购买时需要在同一笔交易中附带 attester 对 (buyer, expiry, tier) 的 Ed25519 签名，签名等级决定累计购买上限
+describe("scy-transfer kyc attestation", () => {
+  let sale: LocalSale;
+  let attester: Keypair;
+  // 等级 0 最多 500 SCY（10 USD），等级 1 最多 5000 SCY
+  const TIER_LIMITS = [scy(500), scy(5_000)];
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  // attester 签名的消息：域标签(16) || program_id(32) || buyer(32) || expiry(8, 小端序) || tier(1)
+  const attestationMessage = (buyer: PublicKey, expiry: bigint, tier: number) => {
+    const fields = Buffer.alloc(9);
+    fields.writeBigInt64LE(expiry, 0);
+    fields.writeUInt8(tier, 8);
+    return Buffer.concat([
+      Buffer.from("scy-transfer:kyc"),
+      sale.program.programId.toBuffer(),
+      buyer.toBuffer(),
+      fields,
+    ]);
+  };
+
+  const buyWithAttestation = (
+    amount: anchor.BN,
+    attestation: { expiry: bigint; tier: number } | null,
+    signer: Keypair = attester
+  ) =>
+    buyWithUsdc(
+      sale,
+      amount,
+      attestation
+        ? {
+            kycAttestation: {
+              expiry: new anchor.BN(attestation.expiry.toString()),
+              tier: attestation.tier,
+            },
+            preInstructions: [
+              Ed25519Program.createInstructionWithPrivateKey({
+                privateKey: signer.secretKey,
+                message: attestationMessage(
+                  sale.buyer.publicKey,
+                  attestation.expiry,
+                  attestation.tier
+                ),
+              }),
+            ],
+          }
+        : {}
+    );
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("purchase should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    attester = Keypair.generate();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    await sale.program.methods
+      .setKycConfig(
+        attester.publicKey,
+        TIER_LIMITS.map((limit) => new anchor.BN(limit.toString()))
+      )
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Accepts a purchase with a valid attestation", async () => {
+    const expiry = (await now(sale.context)) + 3600n;
+    await buyWithAttestation(usd(30), { expiry, tier: 1 });
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_500));
+  });
+
+  it("Rejects missing, expired and forged attestations", async () => {
+    const current = await now(sale.context);
+    await expectError(buyWithAttestation(usd(10), null), /KycAttestationRequired/);
+    await expectError(
+      buyWithAttestation(usd(10), { expiry: current - 1n, tier: 1 }),
+      /KycAttestationExpired/
+    );
+    await expectError(
+      buyWithAttestation(usd(10), { expiry: current + 3600n, tier: 2 }),
+      /InvalidKycTier/
+    );
+    await expectError(
+      buyWithAttestation(usd(10), { expiry: current + 3600n, tier: 1 }, Keypair.generate()),
+      /InvalidKycAttestation/
+    );
+  });
+
+  it("Limits cumulative purchases to the signed tier", async () => {
+    const expiry = (await now(sale.context)) + 3600n;
+    await buyWithAttestation(usd(10), { expiry, tier: 0 }); // 买满等级 0 的 500 SCY
+    await expectError(buyWithAttestation(usd(10), { expiry, tier: 0 }), /KycLimitExceeded/);
+
+    // 升级到等级 1 后可以继续购买
+    await buyWithAttestation(usd(10), { expiry, tier: 1 });
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_000));
+  });
+});
@@ -0,0 +1,90 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  buildMerkleTree,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// Merkle 白名单：买家需要提供 (wallet, max_allocation) 的 proof，累计购买不能超过 max_allocation
+describe("scy-transfer merkle allowlist", () => {
+  let sale: LocalSale;
+  let tree: ReturnType<typeof buildMerkleTree>;
+  const MAX_ALLOCATION = scy(1_000); // 20 USD（默认价格 0.02 USD）
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const buyWithProof = (
+    amount: anchor.BN,
+    allowlistProof: { maxAllocation: anchor.BN; proof: number[][] } | null,
+    allowPartial = false
+  ) => buyWithUsdc(sale, amount, { allowlistProof, allowPartial });
+
+  const buyerProof = (maxAllocation = MAX_ALLOCATION) => ({
+    maxAllocation: new anchor.BN(maxAllocation.toString()),
+    proof: tree.proof(1),
+  });
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    tree = buildMerkleTree([
+      [Keypair.generate().publicKey, scy(5_000)],
+      [sale.buyer.publicKey, MAX_ALLOCATION],
+      [Keypair.generate().publicKey, scy(2_000)],
+    ]);
+    await sale.program.methods
+      .setMerkleRoot(tree.root)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Accepts a valid proof up to the wallet allocation", async () => {
+    await buyWithProof(usd(10), buyerProof());
+    await buyWithProof(usd(10), buyerProof());
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), MAX_ALLOCATION);
+  });
+
+  it("Rejects purchases without a valid proof", async () => {
+    for (const proof of [null, buyerProof(scy(2_000))]) {
+      try {
+        await buyWithProof(usd(10), proof);
+        assert.fail("purchase should be rejected");
+      } catch (err) {
+        assert.match(String(err), proof ? /InvalidMerkleProof/ : /MerkleProofRequired/);
+      }
+    }
+  });
+
+  it("Enforces the allocation across purchases", async () => {
+    await buyWithProof(usd(15), buyerProof());
+    try {
+      await buyWithProof(usd(10), buyerProof());
+      assert.fail("purchase should exceed the allocation");
+    } catch (err) {
+      assert.match(String(err), /WalletAllocationExceeded/);
+    }
+
+    // 允许部分成交时只买到剩余额度
+    await buyWithProof(usd(10), buyerProof(), true);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), MAX_ALLOCATION);
+  });
+
+  it("Skips the check once the root is cleared", async () => {
+    await sale.program.methods
+      .setMerkleRoot(new Array(32).fill(0))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await buyWithProof(usd(30), null);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_500));
+  });
+});
@@ -0,0 +1,109 @@
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import { randomBytes } from "crypto";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  findPda,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 订单幂等：传入 client_order_id 时创建 (buyer, id) 的 Order PDA，后端用同一订单号重试时不会重复扣款
+describe("scy-transfer client order ids", () => {
+  let sale: LocalSale;
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const orderPda = (orderId: Buffer) =>
+    findPda(sale.program, Buffer.from("order"), sale.buyer.publicKey.toBuffer(), orderId);
+
+  const buyWithOrder = (
+    amount: anchor.BN,
+    orderId: Buffer | null,
+    order: PublicKey | null = orderId ? orderPda(orderId) : null
+  ) =>
+    buyWithUsdc(sale, amount, {
+      clientOrderId: orderId ? [...orderId] : null,
+      accounts: { order },
+    });
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Records the order and rejects a replay with the same id", async () => {
+    const orderId = randomBytes(32);
+    await buyWithOrder(usd(10), orderId);
+
+    const order = await sale.program.account.order.fetch(orderPda(orderId));
+    assert.deepEqual(Buffer.from(order.clientOrderId), orderId);
+    assert.equal(order.paymentAmount.toNumber(), 10 * 1e6);
+    assert.equal(order.splAmount.toString(), scy(500).toString());
+
+    try {
+      await buyWithOrder(usd(10), orderId);
+      assert.fail("replayed order should be rejected");
+    } catch (err) {
+      assert.match(String(err), /already in use/);
+    }
+    // 只扣款、发放了一次
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(500));
+    assert.equal(
+      await tokenBalance(sale.context, userUsdcAccount()),
+      BigInt(1e9) - BigInt(10 * 1e6)
+    );
+  });
+
+  it("Accepts different order ids and purchases without an id", async () => {
+    await buyWithOrder(usd(10), randomBytes(32));
+    await buyWithOrder(usd(10), randomBytes(32));
+    await buyWithOrder(usd(10), null);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_500));
+  });
+
+  it("Requires the order account when an id is passed", async () => {
+    try {
+      await buyWithOrder(usd(10), randomBytes(32), null);
+      assert.fail("purchase without the order account should be rejected");
+    } catch (err) {
+      assert.match(String(err), /OrderAccountRequired/);
+    }
+  });
+
+  it("Closes the order and returns the rent to the buyer", async () => {
+    const orderId = randomBytes(32);
+    await buyWithOrder(usd(10), orderId);
+
+    await sale.program.methods
+      .closeOrder()
+      .accounts({ order: orderPda(orderId), buyer: sale.buyer.publicKey })
+      .signers([sale.buyer])
+      .rpc();
+    assert.isNull(await sale.context.banksClient.getAccount(orderPda(orderId)));
+
+    // 其它钱包不能关闭买家的订单
+    const orderId2 = randomBytes(32);
+    await buyWithOrder(usd(10), orderId2);
+    try {
+      await sale.program.methods
+        .closeOrder()
+        .accounts({ order: orderPda(orderId2), buyer: sale.admin.publicKey })
+        .rpc();
+      assert.fail("closing another buyer's order should be rejected");
+    } catch (err) {
+      assert.match(String(err), /ConstraintHasOne|has one/i);
+    }
+  });
+});
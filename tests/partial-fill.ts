@@ -0,0 +1,69 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  SCY_DECIMALS,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 库存不足时的部分成交：只买下剩余库存，并只收取对应的付款
+describe("scy-transfer partial fills", () => {
+  let sale: LocalSale;
+  const inventory = BigInt(10_000) * BigInt(10 ** SCY_DECIMALS); // 10000 SCY = 200 USD
+  const usdcBalance = BigInt(1_000_000_000); // 1000 USDC
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  beforeEach(async () => {
+    sale = await setupLocalSale(inventory);
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, usdcBalance);
+  });
+
+  it("Rejects an oversized purchase without allow_partial", async () => {
+    try {
+      await buyWithUsdc(sale, usd(300));
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "InsufficientSPLBalance");
+    }
+  });
+
+  it("Sells the remaining inventory and charges only for it", async () => {
+    await buyWithUsdc(sale, usd(300), { allowPartial: true });
+
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), inventory);
+    assert.equal(
+      await tokenBalance(sale.context, userUsdcAccount()),
+      usdcBalance - BigInt(200 * 1e6)
+    );
+  });
+
+  it("Charges the tiered price of the remaining inventory", async () => {
+    // 前 5000 SCY 为 0.02 USD，之后为 0.04 USD：剩余库存 10000 SCY 共需 100 + 200 USD
+    await sale.program.methods
+      .setPricingTiers([
+        { supply: new anchor.BN((inventory / BigInt(2)).toString()), priceUsd: new anchor.BN(20_000) },
+        { supply: new anchor.BN((inventory / BigInt(2)).toString()), priceUsd: new anchor.BN(40_000) },
+      ])
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+    await buyWithUsdc(sale, usd(500), { allowPartial: true });
+
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), inventory);
+    assert.equal(
+      await tokenBalance(sale.context, userUsdcAccount()),
+      usdcBalance - BigInt(300 * 1e6)
+    );
+  });
+});
@@ -0,0 +1,142 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  SOL_USD_FEED_ID,
+  SCY_DECIMALS,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setPriceUpdate,
+  findPda,
+  now,
+  tokenBalance,
+  buyWithSol,
+} from "./helpers";
+
+// 价格偏离保护：spot 与 EMA 偏离过大时拒绝购买，可选按二者中较低的价格成交；以及 state 账户扩容
+describe("scy-transfer price guard", () => {
+  let sale: LocalSale;
+  const lamportsToPay = LAMPORTS_PER_SOL; // 1 SOL
+
+  // 按合约的计价方式计算 1 SOL 在给定 SOL/USD 价格下可以买到的 SCY（最小单位）
+  const expectedScy = (solPriceInUsd: number) =>
+    BigInt((solPriceInUsd / 0.02) * 10 ** SCY_DECIMALS);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const buyAtPrice = async (spot: number, ema: number) =>
+    buyWithSol(
+      sale,
+      new anchor.BN(lamportsToPay),
+      setPriceUpdate(
+        sale.context,
+        Keypair.generate().publicKey,
+        SOL_USD_FEED_ID,
+        BigInt(spot * 1e8),
+        -8,
+        await now(sale.context),
+        BigInt(ema * 1e8)
+      )
+    );
+
+  const setPriceGuard = (maxDeviationBps: number, useConservativePrice: boolean) =>
+    sale.program.methods
+      .setPriceGuard(maxDeviationBps, useConservativePrice)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("transaction should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+  });
+
+  it("Rejects purchases when spot deviates too far from EMA", async () => {
+    // 默认最多偏离 5%：150 相对 140 偏离约 7%
+    await expectError(buyAtPrice(150, 140), /PriceDeviationTooHigh/);
+
+    await setPriceGuard(1_000, false);
+    await buyAtPrice(150, 140);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), expectedScy(150));
+  });
+
+  it("Prices at the lower of spot and EMA in conservative mode", async () => {
+    await setPriceGuard(500, true);
+    await buyAtPrice(150, 145);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), expectedScy(145));
+  });
+
+  it("Rejects an invalid price guard", async () => {
+    await expectError(setPriceGuard(0, false), /InvalidPriceGuard/);
+    await expectError(setPriceGuard(10_001, false), /InvalidPriceGuard/);
+  });
+
+  it("Migrates a state account created with an older, shorter layout", async () => {
+    const stateAddress = findPda(sale.program, Buffer.from("state"));
+    const current = await sale.context.banksClient.getAccount(stateAddress);
+    const size = current.data.length;
+    // 模拟升级前的账户：去掉末尾新增的字段
+    sale.context.setAccount(stateAddress, {
+      ...current,
+      data: Buffer.from(current.data).subarray(0, size - 3),
+    });
+
+    await expectError(
+      sale.program.methods
+        .migrateState()
+        .accounts({ admin: sale.buyer.publicKey })
+        .signers([sale.buyer])
+        .rpc(),
+      /Unauthorized/
+    );
+
+    await sale.program.methods
+      .migrateState()
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    const migrated = await sale.context.banksClient.getAccount(stateAddress);
+    assert.equal(migrated.data.length, size);
+
+    const state = await sale.program.account.state.fetch(stateAddress);
+    assert.isFalse(state.vestingScheduleLocked);
+    await buyAtPrice(150, 150);
+  });
+
+  it("Restores price guard defaults when migrating the original state layout", async () => {
+    const stateAddress = findPda(sale.program, Buffer.from("state"));
+    const current = await sale.context.banksClient.getAccount(stateAddress);
+    const size = current.data.length;
+    // 最初版本的 State：discriminator + admin + 三个 Mint 地址
+    sale.context.setAccount(stateAddress, {
+      ...current,
+      data: Buffer.from(current.data).subarray(0, 8 + 32 * 4),
+    });
+
+    await sale.program.methods
+      .migrateState()
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    const migrated = await sale.context.banksClient.getAccount(stateAddress);
+    assert.equal(migrated.data.length, size);
+
+    const state = await sale.program.account.state.fetch(stateAddress);
+    assert.equal(state.maxPriceDeviationBps, 500);
+    assert.isFalse(state.useConservativePrice);
+    assert.equal(state.minPriceSources, 1);
+    assert.isTrue(state.priceOperator.equals(sale.admin.publicKey));
+    assert.isTrue(state.mint.equals(sale.scyMint));
+
+    await buyAtPrice(150, 150);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), expectedScy(150));
+  });
+});
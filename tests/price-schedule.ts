@@ -0,0 +1,77 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  now,
+  warpTo,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 定时调价：到达每一步的 timestamp 后价格自动生效，不需要管理员发交易
+describe("scy-transfer price schedule", () => {
+  let sale: LocalSale;
+  let start: bigint;
+  const DAY = BigInt(86400);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  // 用 10 USDC 购买，返回本次获得的 SCY
+  const buyTenUsd = async () => {
+    const before = await tokenBalance(sale.context, userScyAccount()).catch(
+      () => BigInt(0)
+    );
+    await buyWithUsdc(sale, usd(10));
+    return (await tokenBalance(sale.context, userScyAccount())) - before;
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    start = await now(sale.context);
+    await sale.program.methods
+      .setPriceSchedule([
+        { timestamp: new anchor.BN((start + DAY).toString()), priceUsd: new anchor.BN(25_000) },
+        { timestamp: new anchor.BN((start + BigInt(2) * DAY).toString()), priceUsd: new anchor.BN(40_000) },
+      ])
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Uses the default price before the first step", async () => {
+    assert.equal(await buyTenUsd(), scy(500)); // 0.02 USD
+  });
+
+  it("Switches price at each step", async () => {
+    await warpTo(sale.context, start + DAY);
+    assert.equal(await buyTenUsd(), scy(400)); // 0.025 USD
+
+    await warpTo(sale.context, start + BigInt(2) * DAY - BigInt(1));
+    assert.equal(await buyTenUsd(), scy(400));
+
+    await warpTo(sale.context, start + BigInt(2) * DAY);
+    assert.equal(await buyTenUsd(), scy(250)); // 0.04 USD
+  });
+
+  it("Rejects steps that are not in time order", async () => {
+    try {
+      await sale.program.methods
+        .setPriceSchedule([
+          { timestamp: new anchor.BN((start + DAY).toString()), priceUsd: new anchor.BN(25_000) },
+          { timestamp: new anchor.BN(start.toString()), priceUsd: new anchor.BN(40_000) },
+        ])
+        .accounts({ admin: sale.admin.publicKey })
+        .rpc();
+      assert.fail("schedule should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "InvalidPriceSchedule");
+    }
+  });
+});
@@ -0,0 +1,325 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  SOL_USD_FEED_ID,
+  SCY_DECIMALS,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setPriceUpdate,
+  findPda,
+  now,
+  tokenBalance,
+  buyWithSol,
+} from "./helpers";
+
+// 使用本地构造的 Pyth 价格账户和备用价格账户，测试多价格源取中位数
+describe("scy-transfer price sources", () => {
+  let sale: LocalSale;
+  const lamportsToPay = LAMPORTS_PER_SOL; // 1 SOL
+
+  // 按合约的计价方式计算 1 SOL 在给定 SOL/USD 价格下可以买到的 SCY（最小单位）
+  const expectedScy = (solPriceInUsd: number) =>
+    ((lamportsToPay / LAMPORTS_PER_SOL) * solPriceInUsd / 0.02) *
+    10 ** SCY_DECIMALS;
+
+  const buyWithSources = (
+    priceUpdate: PublicKey,
+    extraSources: PublicKey[] = [],
+    manualPrice: PublicKey | null = null
+  ) =>
+    buyWithSol(sale, new anchor.BN(lamportsToPay), priceUpdate, {
+      remainingAccounts: extraSources,
+      accounts: { manualPrice },
+    });
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const fallbackPriceAddress = () =>
+    findPda(sale.program, Buffer.from("fallback_price"), SOL_USD_FEED_ID);
+
+  const setFallbackPrice = (price: number) =>
+    sale.program.methods
+      .setFallbackPrice(
+        Array.from(SOL_USD_FEED_ID),
+        new anchor.BN(price * 1e8),
+        -8
+      )
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const manualPriceAddress = () =>
+    findPda(sale.program, Buffer.from("manual_price"), SOL_USD_FEED_ID);
+
+  const setManualPrice = (price: number, duration: number) =>
+    sale.program.methods
+      .setManualPrice(
+        Array.from(SOL_USD_FEED_ID),
+        new anchor.BN(price * 1e8),
+        -8,
+        new anchor.BN(duration)
+      )
+      .accounts({ operator: sale.admin.publicKey })
+      .rpc();
+
+  const setMinPriceSources = (count: number) =>
+    sale.program.methods
+      .setMinPriceSources(count)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const setOracleDown = (oracleDown: boolean, operator: Keypair = sale.admin) =>
+    sale.program.methods
+      .setOracleDown(oracleDown)
+      .accounts({ operator: operator.publicKey })
+      .signers([operator])
+      .rpc();
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+  });
+
+  it("Prices a purchase at the median of fresh sources", async () => {
+    const publishTime = await now(sale.context);
+    const primary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    const secondary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(152e8),
+      -8,
+      publishTime - BigInt(1)
+    );
+    await setFallbackPrice(151);
+    await setMinPriceSources(3);
+
+    await buyWithSources(primary, [secondary, fallbackPriceAddress()]);
+
+    const balance = await tokenBalance(sale.context, userScyAccount());
+    assert.approximately(Number(balance), expectedScy(151), 10);
+  });
+
+  it("Prices from the fresh extra sources when the primary feed is stale", async () => {
+    const stale = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      (await now(sale.context)) - BigInt(3600)
+    );
+    const secondary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(140e8),
+      -8,
+      await now(sale.context)
+    );
+    await setFallbackPrice(140);
+    await setMinPriceSources(2);
+
+    // 主价格源过期时不计入，其余两个未过期的价格源满足 min_price_sources
+    await buyWithSources(stale, [secondary, fallbackPriceAddress()]);
+    const balance = await tokenBalance(sale.context, userScyAccount());
+    assert.approximately(Number(balance), expectedScy(140), 10);
+  });
+
+  it("Counts one signed price update only once", async () => {
+    const publishTime = await now(sale.context);
+    // 同一份价格更新写入两个不同的接收账户
+    const primary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    const copy = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    await setMinPriceSources(2);
+
+    try {
+      await buyWithSources(primary, [copy]);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "DuplicatePriceSource");
+    }
+  });
+
+  it("Rejects a purchase without enough fresh sources", async () => {
+    const publishTime = await now(sale.context);
+    const primary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    const stale = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime - BigInt(3600)
+    );
+    await setMinPriceSources(2);
+
+    try {
+      await buyWithSources(primary, [stale]);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "NotEnoughPriceSources");
+    }
+  });
+
+  it("Rejects a purchase when fresh sources disagree", async () => {
+    const publishTime = await now(sale.context);
+    const primary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    await setFallbackPrice(200);
+    await setMinPriceSources(2);
+
+    try {
+      await buyWithSources(primary, [fallbackPriceAddress()]);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "NotEnoughPriceSources");
+    }
+  });
+
+  it("Uses the manual price only while the oracle is marked down and stale", async () => {
+    const publishTime = await now(sale.context);
+    const fresh = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime
+    );
+    const stale = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      publishTime - BigInt(3600)
+    );
+    await setManualPrice(120, 3600);
+
+    // 预言机价格正常时忽略人工价格
+    await buyWithSources(fresh, [], manualPriceAddress());
+    const afterOracleFill = await tokenBalance(sale.context, userScyAccount());
+    assert.approximately(Number(afterOracleFill), expectedScy(150), 10);
+
+    // 预言机价格过期但未标记故障时拒绝购买，不会自动改用人工价格
+    try {
+      await buyWithSources(stale, [], manualPriceAddress());
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "NotEnoughPriceSources");
+    }
+
+    // 管理员标记预言机故障后使用人工价格
+    await setOracleDown(true);
+    await buyWithSources(stale, [], manualPriceAddress());
+    const afterManualFill = await tokenBalance(sale.context, userScyAccount());
+    assert.approximately(
+      Number(afterManualFill - afterOracleFill),
+      expectedScy(120),
+      10
+    );
+
+    // 标记故障期间预言机价格恢复时仍然优先使用预言机价格
+    await buyWithSources(fresh, [], manualPriceAddress());
+    assert.approximately(
+      Number((await tokenBalance(sale.context, userScyAccount())) - afterManualFill),
+      expectedScy(150),
+      10
+    );
+
+    try {
+      await buyWithSources(stale);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "ManualPriceRequired");
+    }
+  });
+
+  it("Lets the price operator mark the oracle down", async () => {
+    const operator = Keypair.generate();
+    const other = Keypair.generate();
+    await sale.program.methods
+      .setPriceOperator(operator.publicKey)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+    try {
+      await setOracleDown(true, other);
+      assert.fail("only admin or price operator may mark the oracle down");
+    } catch (error) {
+      assert.include(String(error), "Unauthorized");
+    }
+
+    await setOracleDown(true, operator);
+    let state = await sale.program.account.state.fetch(findPda(sale.program, Buffer.from("state")));
+    assert.isTrue(state.oracleDown);
+
+    await setOracleDown(false, operator);
+    state = await sale.program.account.state.fetch(findPda(sale.program, Buffer.from("state")));
+    assert.isFalse(state.oracleDown);
+  });
+
+  it("Rejects a manual price longer than the maximum duration", async () => {
+    try {
+      await setManualPrice(120, 2 * 24 * 60 * 60);
+      assert.fail("manual price should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "InvalidManualPriceDuration");
+    }
+  });
+
+  it("Rejects the same source passed twice", async () => {
+    const primary = setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      await now(sale.context)
+    );
+
+    try {
+      await buyWithSources(primary, [primary]);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "DuplicatePriceSource");
+    }
+  });
+});
@@ -0,0 +1,61 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 价格档位：前 1000 SCY 为 0.02 USD，之后为 0.025 USD，跨档位的购买分段计价
+describe("scy-transfer pricing tiers", () => {
+  let sale: LocalSale;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const quote = (amount: anchor.BN) =>
+    sale.program.methods
+      .quote(amount)
+      .accounts({ mint: sale.scyMint })
+      .view();
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    await sale.program.methods
+      .setPricingTiers([
+        { supply: new anchor.BN(scy(1_000).toString()), priceUsd: new anchor.BN(20_000) },
+        { supply: new anchor.BN(scy(1_000).toString()), priceUsd: new anchor.BN(25_000) },
+      ])
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Prices a purchase that crosses a tier boundary piecewise", async () => {
+    // 20 USD 买下第一档的 1000 SCY，剩余 10 USD 按 0.025 买到 400 SCY
+    const quoted = await quote(usd(30));
+    assert.equal(quoted.toString(), scy(1_400).toString());
+
+    await buyWithUsdc(sale, usd(30));
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_400));
+  });
+
+  it("Starts the next purchase from the current position in the tier table", async () => {
+    await buyWithUsdc(sale, usd(10)); // 500 SCY，仍在第一档
+    assert.equal((await quote(usd(20))).toString(), scy(900).toString());
+
+    await buyWithUsdc(sale, usd(20)); // 500 SCY 按 0.02，剩余 10 USD 按 0.025 买到 400 SCY
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_400));
+  });
+
+  it("Keeps the last tier price after the table is exhausted", async () => {
+    await buyWithUsdc(sale, usd(45)); // 买完两个档位
+    assert.equal((await quote(usd(25))).toString(), scy(1_000).toString());
+  });
+});
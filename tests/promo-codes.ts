@@ -0,0 +1,128 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import { createHash } from "crypto";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  now,
+  warpTo,
+  tokenBalance,
+  findPda,
+  buildMerkleTree,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 优惠码：链上只登记 sha256(code)，买家传入明文获得额外的 SCY，次数用完或过期后失效
+describe("scy-transfer promo codes", () => {
+  let sale: LocalSale;
+  let expiry: bigint;
+  const CODE = "LAUNCH10";
+
+  const codeHash = (code: string) => createHash("sha256").update(code).digest();
+
+  const promoPda = (code: string) =>
+    findPda(sale.program, Buffer.from("promo_code"), codeHash(code));
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const buyWithCode = (
+    amount: anchor.BN,
+    code: string | null,
+    promoCode: PublicKey | null = code ? promoPda(CODE) : null,
+    allowlistProof: { maxAllocation: anchor.BN; proof: number[][] } | null = null,
+    allowPartial = false
+  ) =>
+    buyWithUsdc(sale, amount, {
+      allowPartial,
+      allowlistProof,
+      promoCode: code,
+      accounts: { promoCode },
+    });
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("purchase should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    expiry = (await now(sale.context)) + 3600n;
+    await sale.program.methods
+      .registerPromoCode([...codeHash(CODE)], 1_000, 2, new anchor.BN(expiry.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Adds a percentage bonus and decrements the remaining uses", async () => {
+    await buyWithCode(usd(10), CODE); // 500 SCY + 10% 奖励
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(550));
+
+    const promo = await sale.program.account.promoCode.fetch(promoPda(CODE));
+    assert.equal(promo.remainingUses, 1);
+    // 链上只有哈希，没有明文
+    assert.deepEqual(Buffer.from(promo.codeHash), codeHash(CODE));
+  });
+
+  it("Rejects a wrong code for the account", async () => {
+    await expectError(buyWithCode(usd(10), "LAUNCH20"), /InvalidPromoCode/);
+    await expectError(buyWithCode(usd(10), CODE, null), /PromoCodeRequired/);
+  });
+
+  it("Stops working once the uses are exhausted or the code expires", async () => {
+    await buyWithCode(usd(10), CODE);
+    await buyWithCode(usd(10), CODE);
+    await expectError(buyWithCode(usd(10), CODE), /PromoCodeExhausted/);
+
+    const LATE = "LATE";
+    await sale.program.methods
+      .registerPromoCode([...codeHash(LATE)], 1_000, 5, new anchor.BN(expiry.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await warpTo(sale.context, expiry);
+    await expectError(buyWithCode(usd(10), LATE, promoPda(LATE)), /PromoCodeExpired/);
+  });
+
+  it("Counts the bonus against the wallet allocation", async () => {
+    const allocation = scy(1_000);
+    const tree = buildMerkleTree([
+      [Keypair.generate().publicKey, scy(5_000)],
+      [sale.buyer.publicKey, allocation],
+    ]);
+    await sale.program.methods
+      .setMerkleRoot(tree.root)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    const proof = { maxAllocation: new anchor.BN(allocation.toString()), proof: tree.proof(1) };
+
+    await buyWithCode(usd(10), CODE, promoPda(CODE), proof); // 550 SCY
+    // 500 SCY 本身在额度内，加上 10% 奖励后超过额度
+    await expectError(buyWithCode(usd(10), CODE, promoPda(CODE), proof), /WalletAllocationExceeded/);
+
+    // 部分成交时奖励也计入额度，只收取剩余 450 SCY（含奖励）对应的 USDC
+    const usdcBefore = await tokenBalance(sale.context, userUsdcAccount());
+    await buyWithCode(usd(10), CODE, promoPda(CODE), proof, true);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), allocation);
+    const paid = usdcBefore - (await tokenBalance(sale.context, userUsdcAccount()));
+    assert.isTrue(paid < BigInt(10 * 1e6));
+
+    const info = await sale.program.account.buyerInfo.fetch(
+      findPda(sale.program, Buffer.from("buyer"), sale.buyer.publicKey.toBuffer())
+    );
+    assert.equal(BigInt(info.splPurchased.toString()), allocation);
+  });
+});
@@ -0,0 +1,126 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  findPda,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 成交回执：购买时可选创建，按买家和购买序号生成地址，付款方可以关闭回执取回租金
+describe("scy-transfer receipts", () => {
+  let sale: LocalSale;
+
+  const receiptAddress = (index: number, buyer: PublicKey = sale.buyer.publicKey) => {
+    const indexBytes = Buffer.alloc(8);
+    indexBytes.writeBigUInt64LE(BigInt(index));
+    return findPda(
+      sale.program,
+      Buffer.from("receipt"),
+      buyer.toBuffer(),
+      indexBytes
+    );
+  };
+
+  const buyWithReceipt = (
+    amount: anchor.BN,
+    clientRef: number[] | null,
+    receipt: PublicKey | null,
+    recipient: PublicKey | null = null
+  ) => buyWithUsdc(sale, amount, { clientRef, accounts: { recipient, receipt } });
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Writes a receipt for the purchase and closes it", async () => {
+    const clientRef = Array.from(Buffer.alloc(32, 7));
+    await buyWithReceipt(usd(10), null, null); // 不创建回执的购买也会占用一个序号
+    await buyWithReceipt(usd(20), clientRef, receiptAddress(1));
+
+    const receipt = await sale.program.account.receipt.fetch(receiptAddress(1));
+    assert.isTrue(receipt.buyer.equals(sale.buyer.publicKey));
+    assert.isTrue(receipt.payer.equals(sale.buyer.publicKey));
+    assert.equal(receipt.index.toString(), "1");
+    assert.isTrue(receipt.paymentMint.equals(USDC_MINT));
+    assert.equal(receipt.paymentAmount.toString(), (20 * 1e6).toString());
+    assert.equal(receipt.splAmount.toString(), scy(1_000).toString());
+    assert.equal(receipt.price.toString(), (1e8).toString());
+    assert.equal(receipt.exponent, -8);
+    assert.isTrue(receipt.timestamp.gtn(0));
+    assert.deepEqual(receipt.clientRef, clientRef);
+
+    await sale.program.methods
+      .closeReceipt()
+      .accounts({ receipt: receiptAddress(1), payer: sale.buyer.publicKey })
+      .signers([sale.buyer])
+      .rpc();
+    assert.isNull(await sale.context.banksClient.getAccount(receiptAddress(1)));
+  });
+
+  it("Refunds a gift receipt's rent to the payer", async () => {
+    const recipient = Keypair.generate();
+    sale.context.setAccount(recipient.publicKey, {
+      lamports: 1_000_000_000,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    });
+    const receipt = receiptAddress(0, recipient.publicKey);
+    await buyWithReceipt(usd(10), null, receipt, recipient.publicKey);
+
+    const record = await sale.program.account.receipt.fetch(receipt);
+    assert.isTrue(record.buyer.equals(recipient.publicKey));
+    assert.isTrue(record.payer.equals(sale.buyer.publicKey));
+
+    // 接收方没有支付租金，不能关闭回执
+    try {
+      await sale.program.methods
+        .closeReceipt()
+        .accounts({ receipt, payer: recipient.publicKey })
+        .signers([recipient])
+        .rpc();
+      assert.fail("close should be rejected");
+    } catch (err) {
+      assert.match(String(err), /ConstraintHasOne/);
+    }
+
+    const rent = (await sale.context.banksClient.getAccount(receipt)).lamports;
+    const before = (await sale.context.banksClient.getAccount(sale.buyer.publicKey)).lamports;
+    await sale.program.methods
+      .closeReceipt()
+      .accounts({ receipt, payer: sale.buyer.publicKey })
+      .signers([sale.buyer])
+      .rpc();
+    const after = (await sale.context.banksClient.getAccount(sale.buyer.publicKey)).lamports;
+    assert.isAtLeast(Number(after - before), Number(rent) - 10_000);
+  });
+
+  it("Only lets the payer close a receipt", async () => {
+    await buyWithReceipt(usd(10), null, receiptAddress(0));
+
+    const other = Keypair.generate();
+    sale.context.setAccount(other.publicKey, {
+      lamports: 1_000_000_000,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    });
+    try {
+      await sale.program.methods
+        .closeReceipt()
+        .accounts({ receipt: receiptAddress(0), payer: other.publicKey })
+        .signers([other])
+        .rpc();
+      assert.fail("close should be rejected");
+    } catch (err) {
+      assert.match(String(err), /ConstraintHasOne/);
+    }
+  });
+});
@@ -0,0 +1,150 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setTokenAccount,
+  now,
+  warpTo,
+  tokenBalance,
+  findPda,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 推荐计划：钱包注册 Referrer 账户，买家购买时指定推荐人，推荐人按比例获得额外的 SCY 奖励
+describe("scy-transfer referrals", () => {
+  let sale: LocalSale;
+  let referrer: Keypair;
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const referrerPda = (owner: PublicKey) =>
+    findPda(sale.program, Buffer.from("referrer"), owner.toBuffer());
+
+  const register = (wallet: Keypair) =>
+    sale.program.methods
+      .registerReferrer()
+      .accounts({ owner: wallet.publicKey })
+      .signers([wallet])
+      .rpc();
+
+  const buyWithReferrer = (
+    amount: anchor.BN,
+    referrerAccount: PublicKey | null,
+    recipient: PublicKey | null = null
+  ) => buyWithUsdc(sale, amount, { accounts: { recipient, referrer: referrerAccount } });
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    referrer = Keypair.generate();
+    sale.context.setAccount(referrer.publicKey, {
+      lamports: 10 * LAMPORTS_PER_SOL,
+      data: Buffer.alloc(0),
+      owner: SystemProgram.programId,
+      executable: false,
+    });
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    await register(referrer);
+    await sale.program.methods
+      .setReferralReward(500) // 5%
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Accrues rewards and stats to the referrer", async () => {
+    await buyWithReferrer(usd(10), referrerPda(referrer.publicKey));
+    await buyWithReferrer(usd(20), referrerPda(referrer.publicKey));
+
+    const account = await sale.program.account.referrer.fetch(referrerPda(referrer.publicKey));
+    assert.equal(account.referredPurchases.toNumber(), 2);
+    assert.equal(account.referredSpl.toString(), scy(1_500).toString());
+    assert.equal(account.referredUsd.toNumber(), 30 * 1e6);
+    assert.equal(account.pendingRewards.toString(), scy(75).toString());
+
+    // 奖励从库存中预留，不能再出售
+    const state = await sale.program.account.state.fetch(findPda(sale.program, Buffer.from("state")));
+    assert.equal(state.reservedSpl.toString(), scy(75).toString());
+  });
+
+  it("Pays out accrued rewards on claim", async () => {
+    await buyWithReferrer(usd(20), referrerPda(referrer.publicKey));
+    await sale.program.methods
+      .claimReferralRewards()
+      .accounts({ owner: referrer.publicKey, mint: sale.scyMint })
+      .signers([referrer])
+      .rpc();
+
+    const referrerScy = getAssociatedTokenAddressSync(sale.scyMint, referrer.publicKey);
+    assert.equal(await tokenBalance(sale.context, referrerScy), scy(50));
+    const account = await sale.program.account.referrer.fetch(referrerPda(referrer.publicKey));
+    assert.equal(account.pendingRewards.toNumber(), 0);
+    assert.equal(account.claimedRewards.toString(), scy(50).toString());
+  });
+
+  it("Releases reserved rewards when the soft-cap sale fails", async () => {
+    const saleEnd = (await now(sale.context)) + BigInt(3600);
+    await sale.program.methods
+      .setSoftCap(new anchor.BN(1_000_000 * 1e6), new anchor.BN(saleEnd.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await buyWithReferrer(usd(20), referrerPda(referrer.publicKey));
+
+    await warpTo(sale.context, saleEnd);
+    await sale.program.methods.finalizeSale().rpc();
+
+    try {
+      await sale.program.methods
+        .claimReferralRewards()
+        .accounts({ owner: referrer.publicKey, mint: sale.scyMint })
+        .signers([referrer])
+        .rpc();
+      assert.fail("rewards should not be claimable after a failed sale");
+    } catch (err) {
+      assert.match(String(err), /SaleNotSucceeded/);
+    }
+
+    await sale.program.methods
+      .releaseReferralRewards()
+      .accounts({ referrer: referrerPda(referrer.publicKey) })
+      .rpc();
+    await sale.program.methods
+      .claimRefund()
+      .accounts({ owner: sale.buyer.publicKey, userUsdcAta: userUsdcAccount(), userUsdtAta: null })
+      .signers([sale.buyer])
+      .rpc();
+
+    // 推荐奖励和买家额度的预留都已释放，管理员可以取回全部 SCY
+    const account = await sale.program.account.referrer.fetch(referrerPda(referrer.publicKey));
+    assert.equal(account.pendingRewards.toNumber(), 0);
+    assert.equal(account.referredPurchases.toNumber(), 1);
+    const state = await sale.program.account.state.fetch(findPda(sale.program, Buffer.from("state")));
+    assert.equal(state.reservedSpl.toNumber(), 0);
+  });
+
+  it("Rejects self-referral", async () => {
+    await register(sale.buyer);
+    try {
+      await buyWithReferrer(usd(10), referrerPda(sale.buyer.publicKey));
+      assert.fail("self-referral should be rejected");
+    } catch (err) {
+      assert.match(String(err), /SelfReferral/);
+    }
+  });
+
+  it("Rejects a purchase for the referrer's own wallet", async () => {
+    // 付款方不是推荐人，但 SCY 发给推荐人自己，同样视为自我推荐
+    try {
+      await buyWithReferrer(usd(10), referrerPda(referrer.publicKey), referrer.publicKey);
+      assert.fail("self-referral should be rejected");
+    } catch (err) {
+      assert.match(String(err), /SelfReferral/);
+    }
+  });
+});
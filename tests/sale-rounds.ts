@@ -0,0 +1,138 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  findPda,
+  now,
+  warpTo,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 多个销售轮次：每个轮次有独立的价格、时间、额度、钱包上限、白名单和释放规则
+describe("scy-transfer sale rounds", () => {
+  let sale: LocalSale;
+  let start: bigint;
+  const ROUND = 1;
+
+  const roundPurchaseAddress = () =>
+    findPda(
+      sale.program,
+      Buffer.from("round_purchase"),
+      Buffer.from([ROUND]),
+      sale.buyer.publicKey.toBuffer()
+    );
+
+  const allowlistAddress = () =>
+    findPda(
+      sale.program,
+      Buffer.from("allowlist"),
+      Buffer.from([ROUND]),
+      sale.buyer.publicKey.toBuffer()
+    );
+
+  const setSaleRound = (allowlistRequired: boolean) =>
+    sale.program.methods
+      .setSaleRound(ROUND, {
+        priceUsd: new anchor.BN(10_000), // 1 SCY = 0.01 USD
+        startTime: new anchor.BN(start.toString()),
+        endTime: new anchor.BN((start + BigInt(86400)).toString()),
+        allocation: new anchor.BN(scy(50_000).toString()),
+        perWalletCap: new anchor.BN(scy(20_000).toString()),
+        allowlistRequired,
+        vestingSchedule: {
+          tgeUnlockBps: 2_500,
+          cliffDuration: new anchor.BN(0),
+          vestingDuration: new anchor.BN(1000),
+        },
+      })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const buyInRound = (amount: anchor.BN, allowlisted = false, round: number | null = ROUND) =>
+    buyWithUsdc(sale, amount, {
+      roundIndex: round,
+      accounts: { allowlistEntry: allowlisted ? allowlistAddress() : null },
+    });
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    start = await now(sale.context);
+  });
+
+  it("Prices purchases at the round price and vests them from TGE", async () => {
+    await setSaleRound(false);
+    await buyInRound(usd(100)); // 100 USD / 0.01 = 10000 SCY
+
+    const record = await sale.program.account.roundPurchase.fetch(
+      roundPurchaseAddress()
+    );
+    assert.equal(record.purchased.toString(), scy(10_000).toString());
+
+    await sale.program.methods
+      .setTgeTimestamp(new anchor.BN(start.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await warpTo(sale.context, start + BigInt(500));
+    await sale.program.methods
+      .claimRoundVested(ROUND)
+      .accounts({ owner: sale.buyer.publicKey, mint: sale.scyMint })
+      .signers([sale.buyer])
+      .rpc();
+
+    // 25% 在 TGE 释放，剩余部分过去一半时间，共 62.5%
+    const userScy = getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+    assert.equal(await tokenBalance(sale.context, userScy), scy(6_250));
+  });
+
+  it("Enforces the per-wallet cap", async () => {
+    await setSaleRound(false);
+    await buyInRound(usd(150));
+
+    try {
+      await buyInRound(usd(100));
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "RoundLimitExceeded");
+    }
+  });
+
+  it("Requires an allowlist entry when the round is gated", async () => {
+    await setSaleRound(true);
+    try {
+      await buyInRound(usd(100));
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "NotAllowlisted");
+    }
+
+    await sale.program.methods
+      .addToAllowlist(ROUND, sale.buyer.publicKey)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await buyInRound(usd(100), true);
+  });
+
+  it("Rejects purchases outside a round once rounds are required", async () => {
+    await setSaleRound(true);
+    await buyInRound(usd(100), false, null); // 未开启时可以按默认价格购买
+
+    await sale.program.methods
+      .setRoundRequired(true)
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    try {
+      await buyInRound(usd(100), false, null);
+      assert.fail("purchase should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "RoundIndexRequired");
+    }
+  });
+});
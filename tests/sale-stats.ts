@@ -0,0 +1,76 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair } from "@solana/web3.js";
+import { assert } from "chai";
+import {
+  LocalSale,
+  SOL_USD_FEED_ID,
+  USDC_MINT,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setPriceUpdate,
+  setTokenAccount,
+  findPda,
+  now,
+  scy,
+  usd,
+  buyWithUsdc,
+  buyWithSol,
+} from "./helpers";
+
+// 全局销售统计与每个买家的购买记录，按整数计价：1 SCY = 0.02 USD
+describe("scy-transfer sale stats", () => {
+  let sale: LocalSale;
+
+  const saleStatsAddress = () => findPda(sale.program, Buffer.from("sale_stats"));
+  const buyerInfoAddress = () =>
+    findPda(sale.program, Buffer.from("buyer"), sale.buyer.publicKey.toBuffer());
+
+  // 按 150 USD/SOL 支付 1 SOL
+  const buyOneSol = async () =>
+    buyWithSol(
+      sale,
+      new anchor.BN(LAMPORTS_PER_SOL),
+      setPriceUpdate(
+        sale.context,
+        Keypair.generate().publicKey,
+        SOL_USD_FEED_ID,
+        BigInt(150e8),
+        -8,
+        await now(sale.context)
+      )
+    );
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Accumulates sale stats and buyer info across purchases", async () => {
+    await buyOneSol(); // 150 USD = 7500 SCY
+    await buyWithUsdc(sale, usd(100)); // 100 USD = 5000 SCY
+
+    const stats = await sale.program.account.saleStats.fetch(saleStatsAddress());
+    assert.equal(stats.splSold.toString(), scy(12_500).toString());
+    assert.equal(stats.solRaised.toString(), LAMPORTS_PER_SOL.toString());
+    assert.equal(stats.usdcRaised.toString(), (100 * 1e6).toString());
+    assert.equal(stats.usdtRaised.toString(), "0");
+    assert.equal(stats.usdRaised.toString(), (250 * 1e6).toString());
+    assert.equal(stats.buyerCount.toString(), "1");
+    assert.isTrue(stats.lastPurchaseSlot.gtn(0));
+
+    const info = await sale.program.account.buyerInfo.fetch(buyerInfoAddress());
+    assert.isTrue(info.wallet.equals(sale.buyer.publicKey));
+    assert.equal(info.splPurchased.toString(), scy(12_500).toString());
+    assert.equal(info.usdSpent.toString(), (250 * 1e6).toString());
+    assert.equal(info.purchaseCount.toString(), "2");
+  });
+
+  it("Returns the sale stats from get_stats", async () => {
+    await buyWithUsdc(sale, usd(10));
+
+    const stats = await sale.program.methods.getStats().view();
+    assert.equal(stats.splSold.toString(), scy(500).toString());
+    assert.equal(stats.usdRaised.toString(), (10 * 1e6).toString());
+    assert.equal(stats.buyerCount.toString(), "1");
+  });
+});
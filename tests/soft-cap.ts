@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  SOL_USD_FEED_ID,
+  USDC_MINT,
+  USDT_MINT,
+  LAMPORTS_PER_SOL,
+  setupLocalSale,
+  setPriceUpdate,
+  setTokenAccount,
+  findPda,
+  now,
+  warpTo,
+  tokenBalance,
+  buyWithUsdc,
+  buyWithSol,
+} from "./helpers";
+
+// 软顶模式：付款留在托管中，sale_end 之后任何人都可以确定募资结果
+describe("scy-transfer soft cap", () => {
+  let sale: LocalSale;
+  let saleEnd: bigint;
+  const lamportsToPay = LAMPORTS_PER_SOL; // 1 SOL，按 150 USD 计价
+  const usdcToPay = BigInt(100_000_000); // 100 USDC
+
+  const freshSolPrice = async () =>
+    setPriceUpdate(
+      sale.context,
+      Keypair.generate().publicKey,
+      SOL_USD_FEED_ID,
+      BigInt(150e8),
+      -8,
+      await now(sale.context)
+    );
+
+  const userUsdcAccount = () =>
+    getAssociatedTokenAddressSync(USDC_MINT, sale.buyer.publicKey);
+
+  const setSoftCap = (softCapUsd: number) =>
+    sale.program.methods
+      .setSoftCap(new anchor.BN(softCapUsd), new anchor.BN(saleEnd.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const finalizeSale = () => sale.program.methods.finalizeSale().rpc();
+
+  const withdraw = () =>
+    sale.program.methods
+      .withdraw()
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const pdaSolAccount = () => findPda(sale.program, Buffer.from("pda_sol"));
+
+  const balance = async (address: PublicKey) =>
+    (await sale.context.banksClient.getAccount(address)).lamports;
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, usdcToPay);
+    saleEnd = (await now(sale.context)) + BigInt(3600);
+  });
+
+  it("Refunds exactly what was paid when the soft cap is missed", async () => {
+    await setSoftCap(1_000_000 * 1e6);
+    await buyWithSol(sale, new anchor.BN(lamportsToPay), await freshSolPrice());
+    await buyWithUsdc(sale, new anchor.BN(usdcToPay.toString()));
+    assert.equal(await tokenBalance(sale.context, userUsdcAccount()), BigInt(0));
+
+    await warpTo(sale.context, saleEnd);
+    await finalizeSale();
+
+    const lamportsBefore = await balance(sale.buyer.publicKey);
+    await sale.program.methods
+      .claimRefund()
+      .accounts({
+        owner: sale.buyer.publicKey,
+        userUsdcAta: userUsdcAccount(),
+        userUsdtAta: null,
+      })
+      .signers([sale.buyer])
+      .rpc();
+
+    assert.equal(await tokenBalance(sale.context, userUsdcAccount()), usdcToPay);
+    // 退回的 SOL 包括付款本身以及托管 / 额度账户的租金，扣除手续费后至少多出付款金额
+    assert.isAtLeast(
+      Number((await balance(sale.buyer.publicKey)) - lamportsBefore),
+      lamportsToPay - 10_000
+    );
+  });
+
+  it("Unlocks claims and withdrawals once the soft cap is met", async () => {
+    await setSoftCap(100 * 1e6);
+    await buyWithSol(sale, new anchor.BN(lamportsToPay), await freshSolPrice());
+
+    await warpTo(sale.context, saleEnd);
+    try {
+      await buyWithSol(sale, new anchor.BN(lamportsToPay), await freshSolPrice());
+      assert.fail("purchase after sale end should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "SaleClosed");
+    }
+
+    // 确定结果之前付款仍在托管中，提取不会转走 SOL
+    setTokenAccount(sale.context, USDC_MINT, sale.admin.publicKey, BigInt(0));
+    setTokenAccount(sale.context, USDT_MINT, sale.admin.publicKey, BigInt(0));
+    const escrowed = await balance(pdaSolAccount());
+    await withdraw();
+    assert.equal(await balance(pdaSolAccount()), escrowed);
+
+    await finalizeSale();
+    const adminBefore = await balance(sale.admin.publicKey);
+    await withdraw();
+    assert.isAtLeast(
+      Number((await balance(sale.admin.publicKey)) - adminBefore),
+      lamportsToPay - 10_000
+    );
+
+    await sale.program.methods
+      .claimAllocation()
+      .accounts({ owner: sale.buyer.publicKey, mint: sale.scyMint })
+      .signers([sale.buyer])
+      .rpc();
+
+    const userScy = getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+    assert.isAbove(Number(await tokenBalance(sale.context, userScy)), 0);
+
+    try {
+      await sale.program.methods
+        .claimRefund()
+        .accounts({
+          owner: sale.buyer.publicKey,
+          userUsdcAta: null,
+          userUsdtAta: null,
+        })
+        .signers([sale.buyer])
+        .rpc();
+      assert.fail("refund should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "SaleNotFailed");
+    }
+  });
+
+  it("Rejects finalizing before the sale ends", async () => {
+    await setSoftCap(100 * 1e6);
+    try {
+      await finalizeSale();
+      assert.fail("finalize should have been rejected");
+    } catch (error) {
+      assert.include(String(error), "SaleNotEnded");
+    }
+  });
+});
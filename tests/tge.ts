@@ -0,0 +1,108 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  USDT_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  findPda,
+  now,
+  warpTo,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// TGE 后领取模式：购买的 SCY 记入 Allocation，TGE 之后由用户领取，提取时保留尚未领取的部分
+describe("scy-transfer TGE claims", () => {
+  let sale: LocalSale;
+  let tge: bigint;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const setTgeTimestamp = (timestamp: bigint) =>
+    sale.program.methods
+      .setTgeTimestamp(new anchor.BN(timestamp.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const claimAllocation = () =>
+    sale.program.methods
+      .claimAllocation()
+      .accounts({ owner: sale.buyer.publicKey, mint: sale.scyMint })
+      .signers([sale.buyer])
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("transaction should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale(scy(10_000));
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    tge = (await now(sale.context)) + BigInt(1000);
+    await sale.program.methods
+      .setDeliveryMode({ deferredClaim: {} })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Rejects claims before the TGE", async () => {
+    await buyWithUsdc(sale, usd(10)); // 10 USD = 500 SCY
+    await expectError(claimAllocation(), /TgeNotReached/);
+
+    await setTgeTimestamp(tge);
+    await expectError(claimAllocation(), /TgeNotReached/);
+  });
+
+  it("Lets buyers claim the full allocation once the TGE is reached", async () => {
+    await buyWithUsdc(sale, usd(10));
+    await setTgeTimestamp(tge);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), BigInt(0));
+
+    await warpTo(sale.context, tge);
+    await claimAllocation();
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(500));
+
+    const allocation = await sale.program.account.allocation.fetch(
+      findPda(sale.program, Buffer.from("allocation"), sale.buyer.publicKey.toBuffer())
+    );
+    assert.equal(allocation.claimedAmount.toString(), scy(500).toString());
+    await expectError(claimAllocation(), /NothingToClaim/);
+  });
+
+  it("Does not allow moving the TGE once it has started", async () => {
+    await setTgeTimestamp(tge);
+    await setTgeTimestamp(tge + BigInt(500)); // TGE 之前可以调整
+    await warpTo(sale.context, tge + BigInt(500));
+    await expectError(setTgeTimestamp(tge + BigInt(1000)), /TgeAlreadyStarted/);
+  });
+
+  it("Keeps unclaimed allocations in the sale account on withdraw", async () => {
+    await buyWithUsdc(sale, usd(10));
+    setTokenAccount(sale.context, USDC_MINT, sale.admin.publicKey, BigInt(0));
+    setTokenAccount(sale.context, USDT_MINT, sale.admin.publicKey, BigInt(0));
+    await sale.program.methods
+      .withdraw()
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+    const pdaSplAta = findPda(sale.program, Buffer.from("pda_spl_ata"));
+    assert.equal(await tokenBalance(sale.context, pdaSplAta), scy(500));
+
+    await setTgeTimestamp(tge);
+    await warpTo(sale.context, tge);
+    await claimAllocation();
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(500));
+    assert.equal(await tokenBalance(sale.context, pdaSplAta), BigInt(0));
+  });
+});
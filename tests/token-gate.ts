@@ -0,0 +1,124 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  SCY_DECIMALS,
+  setupLocalSale,
+  setMint,
+  setTokenAccount,
+  findPda,
+  now,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 持币门槛：只有持有足够合作方代币的钱包可以在门槛轮次购买，可选按持币数量分配该轮次的购买额度
+describe("scy-transfer token gate", () => {
+  let sale: LocalSale;
+  let gatingMint: PublicKey;
+  const ROUND = 1;
+  const MIN_BALANCE = 100n;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const roundPurchased = async () => {
+    const record = await sale.program.account.roundPurchase.fetch(
+      findPda(
+        sale.program,
+        Buffer.from("round_purchase"),
+        Buffer.from([ROUND]),
+        sale.buyer.publicKey.toBuffer()
+      )
+    );
+    return BigInt(record.purchased.toString());
+  };
+
+  const setTokenGate = async (allocationRate: number) => {
+    const start = await now(sale.context);
+    await sale.program.methods
+      .setSaleRound(ROUND, {
+        priceUsd: new anchor.BN(10_000), // 1 SCY = 0.01 USD
+        startTime: new anchor.BN(start.toString()),
+        endTime: new anchor.BN((start + BigInt(86400)).toString()),
+        allocation: new anchor.BN(scy(50_000).toString()),
+        perWalletCap: new anchor.BN(scy(20_000).toString()),
+        allowlistRequired: false,
+        vestingSchedule: {
+          tgeUnlockBps: 10_000,
+          cliffDuration: new anchor.BN(0),
+          vestingDuration: new anchor.BN(0),
+        },
+      })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await sale.program.methods
+      .setTokenGate(ROUND, gatingMint, new anchor.BN(MIN_BALANCE.toString()), new anchor.BN(allocationRate))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  };
+
+  const buyWithGate = (
+    amount: anchor.BN,
+    gatingTokenAccount: PublicKey | null,
+    round: number | null = ROUND
+  ) => buyWithUsdc(sale, amount, { roundIndex: round, accounts: { gatingTokenAccount } });
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("purchase should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    gatingMint = Keypair.generate().publicKey;
+    setMint(sale.context, gatingMint, sale.admin.publicKey, 0);
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+  });
+
+  it("Requires a gating token account meeting the minimum balance", async () => {
+    await setTokenGate(0);
+    await expectError(buyWithGate(usd(10), null), /GatingTokenAccountRequired/);
+
+    const otherHolder = setTokenAccount(
+      sale.context,
+      gatingMint,
+      Keypair.generate().publicKey,
+      MIN_BALANCE
+    );
+    await expectError(buyWithGate(usd(10), otherHolder), /InvalidGatingTokenAccount/);
+
+    const holding = setTokenAccount(sale.context, gatingMint, sale.buyer.publicKey, MIN_BALANCE - 1n);
+    await expectError(buyWithGate(usd(10), holding), /InsufficientGatingBalance/);
+
+    setTokenAccount(sale.context, gatingMint, sale.buyer.publicKey, MIN_BALANCE);
+    await buyWithGate(usd(10), holding);
+    assert.equal(await roundPurchased(), scy(1_000));
+  });
+
+  it("Scales the round allocation with the gating token balance", async () => {
+    // 每个合作方代币可以购买 5 SCY：持有 200 个代币可以在该轮次累计购买 1000 SCY（10 USD）
+    await setTokenGate(5 * 10 ** SCY_DECIMALS * 1_000_000);
+    const holding = setTokenAccount(sale.context, gatingMint, sale.buyer.publicKey, 200n);
+
+    await buyWithGate(usd(6), holding);
+    await expectError(buyWithGate(usd(5), holding), /GatingAllocationExceeded/);
+    await buyWithGate(usd(4), holding);
+    assert.equal(await roundPurchased(), scy(1_000));
+  });
+
+  it("Does not gate purchases outside the gated round", async () => {
+    await setTokenGate(0);
+    await buyWithGate(usd(10), null, null);
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(500));
+  });
+});
@@ -0,0 +1,106 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  findPda,
+  now,
+  warpTo,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 锁仓模式：购买的 SCY 记入 VestingPosition，从 TGE 开始按释放规则领取
+describe("scy-transfer vesting", () => {
+  let sale: LocalSale;
+  let tge: bigint;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  const vestingPositionAddress = () =>
+    findPda(sale.program, Buffer.from("vesting"), sale.buyer.publicKey.toBuffer());
+
+  const setVestingSchedule = (tgeUnlockBps: number, cliffDuration: number, vestingDuration: number) =>
+    sale.program.methods
+      .setVestingSchedule({
+        tgeUnlockBps,
+        cliffDuration: new anchor.BN(cliffDuration),
+        vestingDuration: new anchor.BN(vestingDuration),
+      })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+
+  const claimVested = () =>
+    sale.program.methods
+      .claimVested()
+      .accounts({ owner: sale.buyer.publicKey, mint: sale.scyMint })
+      .signers([sale.buyer])
+      .rpc();
+
+  const expectError = async (promise: Promise<unknown>, error: RegExp) => {
+    try {
+      await promise;
+      assert.fail("transaction should be rejected");
+    } catch (err) {
+      assert.match(String(err), error);
+    }
+  };
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e9));
+    tge = (await now(sale.context)) + BigInt(1000);
+    await sale.program.methods
+      .setDeliveryMode({ vesting: {} })
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+    await sale.program.methods
+      .setTgeTimestamp(new anchor.BN(tge.toString()))
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Rejects invalid vesting schedules", async () => {
+    await expectError(setVestingSchedule(10_001, 0, 1000), /InvalidVestingSchedule/);
+  });
+
+  it("Releases the TGE unlock, then vests linearly after the cliff", async () => {
+    // 20% 在 TGE 释放，cliff 100 秒，之后 1000 秒线性释放
+    await setVestingSchedule(2_000, 100, 1000);
+    await buyWithUsdc(sale, usd(20)); // 20 USD / 0.02 = 1000 SCY
+
+    const position = await sale.program.account.vestingPosition.fetch(vestingPositionAddress());
+    assert.equal(position.totalAmount.toString(), scy(1_000).toString());
+    await expectError(claimVested(), /NothingToClaim/);
+
+    await warpTo(sale.context, tge + BigInt(50));
+    await claimVested();
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(200));
+    await expectError(claimVested(), /NothingToClaim/);
+
+    // cliff 结束后又过了一半的线性释放时间：20% + 80% / 2 = 60%
+    await warpTo(sale.context, tge + BigInt(600));
+    await claimVested();
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(600));
+
+    await warpTo(sale.context, tge + BigInt(1100));
+    await claimVested();
+    assert.equal(await tokenBalance(sale.context, userScyAccount()), scy(1_000));
+  });
+
+  it("Freezes the schedule after the first vesting purchase so repeat buyers can top up", async () => {
+    await setVestingSchedule(0, 0, 1000);
+    await buyWithUsdc(sale, usd(10));
+    await expectError(setVestingSchedule(5_000, 0, 1000), /VestingScheduleLocked/);
+
+    await buyWithUsdc(sale, usd(10));
+    const position = await sale.program.account.vestingPosition.fetch(vestingPositionAddress());
+    assert.equal(position.totalAmount.toString(), scy(1_000).toString());
+  });
+});
@@ -0,0 +1,60 @@
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { assert } from "chai";
+import {
+  LocalSale,
+  USDC_MINT,
+  setupLocalSale,
+  setTokenAccount,
+  tokenBalance,
+  scy,
+  usd,
+  buyWithUsdc,
+} from "./helpers";
+
+// 大额购买奖励：单笔超过 1 万美元额外获得 5% SCY，超过 5 万美元额外获得 10%
+describe("scy-transfer volume bonus", () => {
+  let sale: LocalSale;
+
+  const userScyAccount = () =>
+    getAssociatedTokenAddressSync(sale.scyMint, sale.buyer.publicKey);
+
+  beforeEach(async () => {
+    sale = await setupLocalSale();
+    setTokenAccount(sale.context, USDC_MINT, sale.buyer.publicKey, BigInt(1e12));
+    await sale.program.methods
+      .setVolumeBonuses([
+        { minUsd: usd(10_000), bonusBps: 500 },
+        { minUsd: usd(50_000), bonusBps: 1_000 },
+      ])
+      .accounts({ admin: sale.admin.publicKey })
+      .rpc();
+  });
+
+  it("Applies the highest tier reached by the purchase", async () => {
+    await buyWithUsdc(sale, usd(9_000)); // 未达到档位：450,000 SCY
+    await buyWithUsdc(sale, usd(10_000)); // 500,000 SCY + 5%
+    await buyWithUsdc(sale, usd(50_000)); // 2,500,000 SCY + 10%
+    assert.equal(
+      await tokenBalance(sale.context, userScyAccount()),
+      scy(450_000 + 525_000 + 2_750_000)
+    );
+  });
+
+  it("Counts the bonus toward MAX_PURCHASE", async () => {
+    // 4,800,000 SCY 加上 10% 奖励后超过 5,000,000 的单笔上限
+    try {
+      await buyWithUsdc(sale, usd(96_000));
+      assert.fail("purchase should exceed MAX_PURCHASE");
+    } catch (err) {
+      assert.match(String(err), /PurchaseAmountTooHigh/);
+    }
+  });
+
+  it("Includes the bonus in quotes", async () => {
+    const quoted = await sale.program.methods
+      .quote(usd(10_000))
+      .accounts({ mint: sale.scyMint })
+      .view();
+    assert.equal(quoted.toString(), scy(525_000).toString());
+  });
+});